@@ -1 +1,2 @@
 pub mod craft_repo;
+pub mod target;
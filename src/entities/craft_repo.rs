@@ -1,3 +1,4 @@
+use crate::entities::target::{PseudoRule, PseudoStat, TargetExpr, TargetMode};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
@@ -7,6 +8,93 @@ pub struct ModItem {
     pub generation_type: String,
     pub representation: String,
     pub mod_key: String,
+    pub influence: Option<Influence>,
+    /// mod type shared by all tiers, e.g. "IncreasedLife"
+    pub family: String,
+    /// implicit tags of mod, e.g. "life", "elemental"
+    pub tags: Vec<String>,
+    /// only one mod of a group can be on item
+    pub groups: Vec<String>,
+    /// tier within family on the base regardless of item level, 1 is the best
+    pub tier: usize,
+    pub domain: String,
+    pub stat_ids: Vec<String>,
+}
+
+#[cfg(test)]
+impl ModItem {
+    /// Single tier prefix of its own family and group, override fields with `..ModItem::for_test(key)`
+    pub fn for_test(mod_key: &str) -> ModItem {
+        ModItem {
+            required_level: 1,
+            weight: 1000,
+            generation_type: "prefix".to_string(),
+            representation: mod_key.to_string(),
+            mod_key: mod_key.to_string(),
+            influence: None,
+            family: mod_key.to_string(),
+            tags: vec![],
+            groups: vec![mod_key.to_string()],
+            tier: 1,
+            domain: "item".to_string(),
+            stat_ids: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Influence {
+    Shaper,
+    Elder,
+    Crusader,
+    Hunter,
+    Redeemer,
+    Warlord,
+}
+
+impl Influence {
+    pub const ALL: [Influence; 6] = [
+        Influence::Shaper,
+        Influence::Elder,
+        Influence::Crusader,
+        Influence::Hunter,
+        Influence::Redeemer,
+        Influence::Warlord,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Influence::Shaper => "Shaper",
+            Influence::Elder => "Elder",
+            Influence::Crusader => "Crusader",
+            Influence::Hunter => "Hunter",
+            Influence::Redeemer => "Redeemer",
+            Influence::Warlord => "Warlord",
+        }
+    }
+
+    /// suffix of spawn weight tags, e.g. "helmet_basilisk" for hunter's helmet
+    fn tag_suffix(&self) -> &'static str {
+        match self {
+            Influence::Shaper => "shaper",
+            Influence::Elder => "elder",
+            Influence::Crusader => "crusader",
+            Influence::Hunter => "basilisk",
+            Influence::Redeemer => "eyrie",
+            Influence::Warlord => "adjudicator",
+        }
+    }
+
+    /// Synthetic tag of influenced base, "helmet" -> "helmet_shaper"
+    pub fn tag_for(&self, base_tag: &str) -> String {
+        format!("{}_{}", base_tag, self.tag_suffix())
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Influence> {
+        Influence::ALL
+            .into_iter()
+            .find(|i| tag.ends_with(&format!("_{}", i.tag_suffix())))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -15,12 +103,166 @@ pub struct ItemBase {
     pub name: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModsTableSort {
+    ModKey,
+    Weight,
+    /// share of prefix or suffix pool
+    Share,
+    RequiredLevel,
+    GenerationType,
+    Tags,
+}
+
+/// Filter chips of mods table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModsTableFilter {
+    pub generation_type: Option<String>,
+    pub tag: Option<String>,
+    pub min_level: u64,
+}
+
+/// Tier of mod within its family, tier 1 is the best one
+#[derive(Debug, Clone)]
+pub struct ModTier {
+    pub tier: usize,
+    pub mod_item: ModItem,
+    /// share of the family weight
+    pub share: f64,
+}
+
+/// All tiers of one mod type available for item
+#[derive(Debug, Clone)]
+pub struct ModFamily {
+    pub family: String,
+    pub generation_type: String,
+    pub total_weight: u32,
+    pub tiers: Vec<ModTier>,
+}
+
+/// Cluster jewel enchant, its tag opens notables of the passive skill type
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterEnchant {
+    pub tag: String,
+    pub name: String,
+}
+
+/// Currency spent on a craft, e.g. 4 of "CurrencyRerollRare"
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrencyCost {
+    pub currency: String,
+    pub count: u32,
+}
+
+/// Explicit mod crafted on crafting bench
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchCraft {
+    pub mod_key: String,
+    pub family: String,
+    pub generation_type: String,
+    pub representation: String,
+    pub cost: Vec<CurrencyCost>,
+}
+
+/// Max count of prefixes and suffixes on item, see `usecases::rules`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AffixLimits {
+    pub prefixes: usize,
+    pub suffixes: usize,
+}
+
+/// Kind of item with its own affix rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ItemCategory {
+    #[default]
+    Equipment,
+    Flask,
+    Jewel,
+    AbyssJewel,
+    ClusterJewel,
+}
+
+impl ItemCategory {
+    /// Category by RePoE domain of base
+    pub fn from_domain(domain: &str) -> ItemCategory {
+        match domain {
+            "flask" => ItemCategory::Flask,
+            "misc" => ItemCategory::Jewel,
+            "abyss_jewel" => ItemCategory::AbyssJewel,
+            "affliction_jewel" => ItemCategory::ClusterJewel,
+            _ => ItemCategory::Equipment,
+        }
+    }
+}
+
+/// Mod outside of prefixes and suffixes, chaos orb doesn't change it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImplicitKind {
+    Implicit,
+    Enchant,
+    Corrupted,
+    Eldritch,
+    Synthesis,
+}
+
+impl ImplicitKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImplicitKind::Implicit => "implicit",
+            ImplicitKind::Enchant => "enchant",
+            ImplicitKind::Corrupted => "corrupted",
+            ImplicitKind::Eldritch => "eldritch",
+            ImplicitKind::Synthesis => "synthesis",
+        }
+    }
+
+    /// Kind of mod by RePoE generation type, "implicit" is set for base's own implicits
+    pub fn from_generation_type(generation_type: &str) -> Option<ImplicitKind> {
+        match generation_type {
+            "implicit" => Some(ImplicitKind::Implicit),
+            "enchantment" => Some(ImplicitKind::Enchant),
+            "corrupted" => Some(ImplicitKind::Corrupted),
+            "exarch_implicit" | "eater_implicit" => Some(ImplicitKind::Eldritch),
+            "synthesis_a" | "synthesis_globals" | "synthesis_bonus" => {
+                Some(ImplicitKind::Synthesis)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Implicit or enchant of pasted item, mod id is None when text is unknown for the base
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemImplicit {
+    pub kind: ImplicitKind,
+    pub text: String,
+    pub mod_id: Option<String>,
+}
+
+/// Mod header of advanced item description, e.g. `{ Prefix Modifier "Remora's" (Tier: 1) }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModHint {
+    pub generation_type: Option<String>,
+    pub name: Option<String>,
+    pub tier: Option<usize>,
+}
+
+/// Mod id of item text, warning when header and text point to different mods
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModResolution {
+    pub mod_id: String,
+    pub warning: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ModsQuery {
     pub string_query: String,
     pub item_level: u64,
     pub item_base: String,
     pub selected_mods: Vec<ModItem>,
+    pub influences: Vec<Influence>,
+    /// passive skill tag of cluster jewel enchant
+    pub cluster_enchant: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,13 +286,46 @@ pub trait CraftRepo {
         item_name: &str,
         mod_name: &str,
     ) -> Result<String, String>;
+    /// Mod id by text, header details pick one of mods with the same text.
+    /// Error when text still fits several mods
+    fn string_to_mod_with_hint(
+        &self,
+        item_class: &str,
+        item_name: &str,
+        mod_name: &str,
+        hint: &ModHint,
+    ) -> Result<String, String>;
+    /// Implicits of base with enchants, corrupted, eldritch and synthesis implicits for it
+    fn find_implicit_mods(&self, search: &ModsQuery) -> Vec<ModItem>;
+    /// Implicit or enchant mod id by its text
+    fn string_to_implicit(&self, item_name: &str, mod_name: &str) -> Result<String, String>;
+    /// Mod ids of item's mod texts by header details with text as fallback and cross-check.
+    /// Pool of base is built once for all mods of item
+    fn resolve_mods(
+        &self,
+        item_class: &str,
+        item_name: &str,
+        mods: &[(String, ModHint)],
+    ) -> Result<Vec<ModResolution>, String>;
     fn get_weight_of_target_and_better_mods(
         &self,
         query: &ModsQuery,
         target_mod_key: String,
     ) -> u32;
+    /// Mods counted in weight of target and better mods with their weights
+    fn get_target_and_better_mods(
+        &self,
+        query: &ModsQuery,
+        target_mod_key: &str,
+    ) -> Vec<(String, u32)>;
     fn get_affected_weight_of_target_mod(&self, query: &ModsQuery) -> u32;
     fn get_subset_of_mods(&self, mod_id: &str, item_base: &str) -> Result<HashSet<String>, String>;
+    /// Enchants of cluster jewel base, empty for other bases
+    fn get_cluster_jewel_enchants(&self, item_base: &str) -> Vec<ClusterEnchant>;
+    /// Explicit mods of crafting bench available for item base
+    fn get_bench_crafts(&self, item_base: &str) -> Vec<BenchCraft>;
+    /// Category of item base, equipment for unknown base
+    fn get_item_category(&self, item_base: &str) -> ItemCategory;
     fn representation_by_mod_id(&self, mod_id: &str) -> String;
 }
 
@@ -58,8 +333,24 @@ pub struct Data {
     pub mods_table: Vec<ModItem>,
     pub item_classes: Vec<String>,
     pub item_bases: Vec<ItemBase>,
+    pub mod_families: Vec<ModFamily>,
+    /// weight of whole prefix/suffix pool by generation type, selected mods' groups excluded
+    pub pool_weight_by_generation_type: HashMap<String, u32>,
+    pub mod_tags: Vec<String>,
+    pub search_error: Option<String>,
+    pub cluster_enchants: Vec<ClusterEnchant>,
     pub item_class_by_base_name: HashMap<String, String>,
     pub estimation: Option<Result<Estimation, String>>,
+    pub magic_estimation: Option<Result<MagicEstimation, String>>,
+    pub reforge_estimation: Option<Result<ReforgeEstimation, String>>,
+    pub base_ranking: Option<Result<Vec<BaseRanking>, String>>,
+    pub ilvl_report: Option<Result<IlvlReport, String>>,
+    pub recipes: Option<Result<Vec<Recipe>, String>>,
+    pub meta_craft_estimation: Option<Result<MetaCraftEstimation, String>>,
+    pub single_action_estimation: Option<Result<SingleActionEstimation, String>>,
+    /// implicit and enchant mods of base, only when implicits table is shown
+    pub implicit_mods: Option<Vec<ModItem>>,
+    pub item_implicits: Option<Result<Vec<ItemImplicit>, String>>,
 }
 
 impl Default for Data {
@@ -68,8 +359,22 @@ impl Default for Data {
             mods_table: Vec::new(),
             item_classes: Vec::new(),
             item_bases: Vec::new(),
+            mod_families: Vec::new(),
+            pool_weight_by_generation_type: HashMap::new(),
+            mod_tags: Vec::new(),
+            search_error: None,
+            cluster_enchants: Vec::new(),
             item_class_by_base_name: HashMap::new(),
             estimation: None,
+            magic_estimation: None,
+            reforge_estimation: None,
+            base_ranking: None,
+            ilvl_report: None,
+            recipes: None,
+            meta_craft_estimation: None,
+            single_action_estimation: None,
+            implicit_mods: None,
+            item_implicits: None,
         }
     }
 }
@@ -86,11 +391,40 @@ pub struct UiStates {
     pub item_level: String,
     pub max_autocraft_tries: String,
     pub selected: Vec<ModItem>,
+    pub excluded: Vec<ModItem>,
+    pub target_mode: TargetMode,
+    pub target_min_count: usize,
+    pub min_open_prefixes: usize,
+    pub min_open_suffixes: usize,
+    pub custom_target: String,
+    pub pseudo_rules: Vec<PseudoRule>,
+    pub pseudo_stat_choice: PseudoStat,
+    pub pseudo_min_value: f64,
+    pub craft_mode: CraftMode,
+    pub reforge: Option<Reforge>,
+    pub meta_crafts: Vec<MetaCraft>,
+    pub influences: Vec<Influence>,
+    pub cluster_enchant: Option<String>,
+    pub grouped_mods_view: bool,
+    /// rank all bases of item class by selected mods
+    pub compare_bases: bool,
+    /// show item level breakpoints of selected mods
+    pub ilvl_breakpoints: bool,
+    pub plan_recipes: bool,
+    /// show implicits and enchants of base instead of explicit mods
+    pub implicits_table: bool,
+    /// done steps of recipes, "recipe name/step index"
+    pub recipe_checklist: HashSet<String>,
+    pub mods_table_sort: ModsTableSort,
+    pub mods_table_sort_descending: bool,
+    pub mods_table_filter: ModsTableFilter,
+    pub currency_positions: CurrencyPositions,
     pub selected_item_class_as_filter: String,
     pub selected_item_base_as_filter: String,
     pub selected_item_level_as_filter: u64,
     pub selected_max_autocraft_tries: u64,
     pub messages: Vec<Message>,
+    pub backend_status: BackendStatus,
 }
 
 impl Default for UiStates {
@@ -102,30 +436,480 @@ impl Default for UiStates {
             max_autocraft_tries: "5".to_string(),
 
             selected: vec![],
+            excluded: vec![],
+            target_mode: TargetMode::All,
+            target_min_count: 1,
+            min_open_prefixes: 0,
+            min_open_suffixes: 0,
+            custom_target: "".to_string(),
+            pseudo_rules: vec![],
+            pseudo_stat_choice: PseudoStat::TotalResistance,
+            pseudo_min_value: 80.0,
+            craft_mode: CraftMode::Chaos,
+            reforge: None,
+            meta_crafts: vec![],
+            influences: vec![],
+            cluster_enchant: None,
+            grouped_mods_view: false,
+            compare_bases: false,
+            ilvl_breakpoints: false,
+            plan_recipes: false,
+            implicits_table: false,
+            recipe_checklist: HashSet::new(),
+            mods_table_sort: ModsTableSort::ModKey,
+            mods_table_sort_descending: false,
+            mods_table_filter: ModsTableFilter::default(),
+            currency_positions: CurrencyPositions::default(),
             selected_item_class_as_filter: "Helmet".to_string(),
             selected_item_base_as_filter: "Iron Hat".to_string(),
             selected_item_level_as_filter: 100,
             selected_max_autocraft_tries: 5,
             messages: vec![],
+            backend_status: BackendStatus::Loading,
         }
     }
 }
-#[derive(Debug, PartialEq)]
+impl UiStates {
+    /// Request to backend from current states,
+    /// cluster enchant is checked by backend against the selected base
+    pub fn to_request(&self, id: u64, events: Vec<UiEvents>) -> CraftRequest {
+        CraftRequest {
+            id,
+            events,
+            item_class: self.selected_item_class_as_filter.clone(),
+            query: ModsQuery {
+                string_query: self.filter_string.clone(),
+                item_base: self.selected_item_base_as_filter.clone(),
+                item_level: self.selected_item_level_as_filter,
+                selected_mods: self.selected.clone(),
+                influences: self.influences.clone(),
+                cluster_enchant: self.cluster_enchant.clone(),
+            },
+            target: self.target_expression(),
+            craft_mode: self.craft_mode,
+            reforge: self.reforge.clone(),
+            meta_crafts: self.meta_crafts.clone(),
+            item_string: self.item_string.clone(),
+            mods_table_filter: self.mods_table_filter.clone(),
+            mods_table_sort: self.mods_table_sort,
+            mods_table_sort_descending: self.mods_table_sort_descending,
+            compare_bases: self.compare_bases,
+            ilvl_breakpoints: self.ilvl_breakpoints,
+            plan_recipes: self.plan_recipes,
+            implicits_table: self.implicits_table,
+        }
+    }
+
+    pub fn autocraft_settings(&self) -> AutocraftSettings {
+        AutocraftSettings {
+            target: self.target_expression(),
+            craft_mode: self.craft_mode,
+            max_tries: self.selected_max_autocraft_tries,
+        }
+    }
+
+    /// Target built from selected/excluded mods,
+    /// custom json expression overrides it when provided
+    pub fn target_expression(&self) -> Result<TargetExpr, String> {
+        if !self.custom_target.trim().is_empty() {
+            return TargetExpr::from_json(&self.custom_target);
+        }
+        let selected = TargetExpr::all_of(&self.selected);
+        let mut exprs = match (self.target_mode, selected) {
+            (TargetMode::All, TargetExpr::And { exprs }) => exprs,
+            (TargetMode::Any, TargetExpr::And { exprs }) if !exprs.is_empty() => {
+                vec![TargetExpr::Or { exprs }]
+            }
+            (TargetMode::AtLeast, TargetExpr::And { exprs }) if !exprs.is_empty() => {
+                vec![TargetExpr::AtLeast {
+                    count: self.target_min_count,
+                    exprs,
+                }]
+            }
+            _ => vec![],
+        };
+        exprs.extend(self.excluded.iter().map(|m| TargetExpr::Not {
+            expr: Box::new(TargetExpr::Mod {
+                mod_id: m.mod_key.clone(),
+            }),
+        }));
+        exprs.extend(self.pseudo_rules.iter().map(|r| TargetExpr::Pseudo {
+            stat: r.stat,
+            min: r.min,
+        }));
+        if self.min_open_prefixes > 0 {
+            exprs.push(TargetExpr::OpenPrefixes {
+                count: self.min_open_prefixes,
+            });
+        }
+        if self.min_open_suffixes > 0 {
+            exprs.push(TargetExpr::OpenSuffixes {
+                count: self.min_open_suffixes,
+            });
+        }
+        Ok(TargetExpr::And { exprs })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Estimation {
     pub probability: f64,
+    /// chaos orbs expected to hit the target, None when it can't be rolled
+    pub expected_tries: Option<f64>,
+    /// contributions of affix count variants, empty when target is simulated
+    pub variants: Vec<VariantContribution>,
+    pub prefix_pool_weight: u32,
+    pub suffix_pool_weight: u32,
+    pub target_weights: Vec<TargetWeight>,
+    /// affix count ratios of the item are guessed, e.g. for rare jewels
+    pub approximate: bool,
 }
 
-#[derive(PartialEq)]
+/// Chaos roll with fixed count of prefixes and suffixes
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantContribution {
+    pub prefix_count: usize,
+    pub suffix_count: usize,
+    /// chance of the roll to have this affix counts
+    pub ratio: f64,
+    /// chance to hit selected mods with this affix counts
+    pub probability: f64,
+}
+
+/// Weight of target mod with its better tiers, the ones accepted instead of target
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetWeight {
+    pub mod_key: String,
+    pub generation_type: String,
+    pub weight: u32,
+    pub pool_weight: u32,
+    /// mods counted in weight by repo, better tiers first
+    pub counted_mods: Vec<String>,
+}
+
+/// Chaos estimation of selected mods on one base of item class
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseRanking {
+    pub item_base: String,
+    pub required_level: u64,
+    pub probability: f64,
+    /// chaos orbs expected to hit the target, None when base can't roll it
+    pub expected_cost: Option<f64>,
+    /// target mod families the base can't roll
+    pub missing_families: Vec<String>,
+    /// estimation error of the base, other bases are ranked anyway
+    pub error: Option<String>,
+}
+
+/// Item level a tier of target family unlocks at
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierUnlock {
+    pub family: String,
+    pub tier: usize,
+    pub mod_key: String,
+    pub item_level: u64,
+}
+
+/// Chance to hit selected mods or better ones starting from item level
+#[derive(Debug, Clone, PartialEq)]
+pub struct IlvlBreakpoint {
+    pub item_level: u64,
+    pub probability: f64,
+    /// target family tiers unlocked at this level
+    pub unlocked: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IlvlReport {
+    pub tiers: Vec<TierUnlock>,
+    pub breakpoints: Vec<IlvlBreakpoint>,
+}
+
+/// Bench mod which allows more than one crafted mod, required by several meta-crafts
+pub const MULTIPLE_CRAFTED_MODS_KEY: &str = "StrIntMasterItemGenerationCanHaveMultipleCraftedMods";
+
+/// Meta-crafts of bench changing how item is rerolled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetaCraft {
+    PrefixesCannotBeChanged,
+    SuffixesCannotBeChanged,
+    CannotRollAttackMods,
+    CannotRollCasterMods,
+}
+
+impl MetaCraft {
+    pub const ALL: [MetaCraft; 4] = [
+        MetaCraft::PrefixesCannotBeChanged,
+        MetaCraft::SuffixesCannotBeChanged,
+        MetaCraft::CannotRollAttackMods,
+        MetaCraft::CannotRollCasterMods,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetaCraft::PrefixesCannotBeChanged => "Prefixes Cannot Be Changed",
+            MetaCraft::SuffixesCannotBeChanged => "Suffixes Cannot Be Changed",
+            MetaCraft::CannotRollAttackMods => "Cannot Roll Attack Modifiers",
+            MetaCraft::CannotRollCasterMods => "Cannot Roll Caster Modifiers",
+        }
+    }
+
+    pub fn mod_key(&self) -> &'static str {
+        match self {
+            MetaCraft::PrefixesCannotBeChanged => "StrMasterItemGenerationCannotChangePrefixes",
+            MetaCraft::SuffixesCannotBeChanged => "DexMasterItemGenerationCannotChangeSuffixes",
+            MetaCraft::CannotRollAttackMods => "IntMasterItemGenerationCannotRollAttackAffixes",
+            MetaCraft::CannotRollCasterMods => "StrDexMasterItemGenerationCannotRollCasterAffixes",
+        }
+    }
+
+    /// affix side kept by chaos orb
+    pub fn locked_side(&self) -> Option<&'static str> {
+        match self {
+            MetaCraft::PrefixesCannotBeChanged => Some("prefix"),
+            MetaCraft::SuffixesCannotBeChanged => Some("suffix"),
+            _ => None,
+        }
+    }
+
+    /// tag of mods which can't be rolled
+    pub fn blocked_tag(&self) -> Option<&'static str> {
+        match self {
+            MetaCraft::CannotRollAttackMods => Some("attack"),
+            MetaCraft::CannotRollCasterMods => Some("caster"),
+            _ => None,
+        }
+    }
+}
+
+/// Chaos orb on item with meta-crafts, they are crafted again before every chaos
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaCraftEstimation {
+    pub probability: f64,
+    /// chaos orb and meta-crafts in chaos orbs
+    pub cost_per_try: f64,
+    pub expected_cost: Option<f64>,
+    /// item hits target after scouring orb clears unlocked side, None without locked side
+    pub scour_hits_target: Option<bool>,
+    /// scouring orb and meta-crafts in chaos orbs, None without locked side
+    pub scour_cost: Option<f64>,
+}
+
+/// Outcomes of one currency orb used on current item
+#[derive(Debug, Clone, PartialEq)]
+pub struct SingleActionEstimation {
+    pub open_prefixes: usize,
+    pub open_suffixes: usize,
+    /// exalted orb adds one of target mods
+    pub exalt_adds_target: f64,
+    /// item hits target after exalted orb
+    pub exalt_hits_target: f64,
+    /// annulment removes mod not needed by target
+    pub annul_removes_unwanted: f64,
+    /// item still hits target after annulment
+    pub annul_keeps_target: f64,
+    /// item hits target after aisling, it annuls a mod and adds veiled one
+    pub aisling_hits_target: f64,
+    /// veiled chaos hits target, veiled mod takes one affix slot
+    pub veiled_chaos_hits_target: f64,
+}
+
+/// What happens with item when step of recipe fails
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepFailure {
+    /// step is repeated on the same item, e.g. alteration spam
+    Retry,
+    /// item is spoiled, recipe starts from the first step
+    Restart,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipeStep {
+    pub name: String,
+    pub probability: f64,
+    /// chaos orbs spent on one try
+    pub cost: f64,
+    pub on_fail: StepFailure,
+}
+
+/// Sequence of crafting steps leading to target
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub name: String,
+    pub steps: Vec<RecipeStep>,
+    /// chaos orbs expected to finish recipe, None when it can't be finished
+    pub expected_cost: Option<f64>,
+}
+
+/// Alteration spam on magic item with optional augmentation and regal steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagicEstimation {
+    /// alteration alone hits magic targets
+    pub alteration_probability: f64,
+    /// alteration followed by augmentation when it is useful
+    pub augmentation_probability: f64,
+    /// share of alterations followed by augmentation
+    pub augmentation_usage: f64,
+    pub expected_alterations: f64,
+    pub expected_augmentations: f64,
+    /// regal adds the rest target to finished magic item
+    pub regal_probability: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CraftMode {
+    Chaos,
+    Alteration,
+}
+
+impl CraftMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CraftMode::Chaos => "chaos (rare)",
+            CraftMode::Alteration => "alteration (magic)",
+        }
+    }
+}
+
+/// Mod tags offered by harvest reforges
+pub const REFORGE_TAGS: &[&str] = &[
+    "attack",
+    "caster",
+    "chaos",
+    "cold",
+    "critical",
+    "defences",
+    "fire",
+    "life",
+    "lightning",
+    "physical",
+    "speed",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReforgeKind {
+    /// new random modifiers including a modifier with tag
+    Guaranteed,
+    /// modifiers with tag are more common
+    MoreLikely,
+    /// modifiers with tag are less common
+    LessLikely,
+}
+
+impl ReforgeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReforgeKind::Guaranteed => "including",
+            ReforgeKind::MoreLikely => "more likely",
+            ReforgeKind::LessLikely => "less likely",
+        }
+    }
+}
+
+/// Harvest reforge of rare item by mod tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reforge {
+    pub tag: String,
+    pub kind: ReforgeKind,
+}
+
+/// Chance to roll target mod (or better tier) with chaos and with reforge
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetProbability {
+    pub mod_key: String,
+    pub chaos: f64,
+    pub reforge: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReforgeEstimation {
+    pub chaos_probability: f64,
+    pub reforge_probability: f64,
+    pub per_target: Vec<TargetProbability>,
+}
+
+/// Screen positions of currency used by autocraft besides currency in hand
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CurrencyPositions {
+    pub alteration: Option<(f64, f64)>,
+    pub augmentation: Option<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum UiEvents {
     Started,
     ChangeModFilter,
     ChangeItemBase,
     AddToSelectedMods,
     CleanSelectedMods,
+    ChangeTarget,
+    ChangeCraftMode,
+    ChangeReforge,
+    ChangeMetaCrafts,
+    ChangeInfluence,
+    ChangeClusterEnchant,
+    ChangeModsTableView,
+    ChangeBaseComparison,
+    ChangeIlvlBreakpoints,
+    ChangeRecipePlanner,
+    ChangeImplicitsTable,
     InsertionItemData,
+    /// repo is created again from data files
+    ReloadData,
 }
 
 #[derive(PartialEq)]
 pub enum BackEvents {
     Error(String),
+    BackendLoading,
+    BackendReady,
+    /// worker can't serve requests until data is reloaded
+    BackendFailed(String),
+    /// data files were edited, repo should be reloaded
+    DataChanged,
+    /// key listener saved positions of currency
+    CurrencyPositions(CurrencyPositions),
+}
+
+/// Autocraft part of ui states, ui sends it to key listener when it changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutocraftSettings {
+    pub target: Result<TargetExpr, String>,
+    pub craft_mode: CraftMode,
+    pub max_tries: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendStatus {
+    Loading,
+    Ready,
+    Unavailable(String),
+}
+
+/// Backend command with everything needed for computation copied from ui states,
+/// events of one frame are collapsed into one request
+#[derive(Debug, Clone)]
+pub struct CraftRequest {
+    pub id: u64,
+    pub events: Vec<UiEvents>,
+    pub item_class: String,
+    pub query: ModsQuery,
+    pub target: Result<TargetExpr, String>,
+    pub craft_mode: CraftMode,
+    pub reforge: Option<Reforge>,
+    pub meta_crafts: Vec<MetaCraft>,
+    pub item_string: String,
+    pub mods_table_filter: ModsTableFilter,
+    pub mods_table_sort: ModsTableSort,
+    pub mods_table_sort_descending: bool,
+    pub compare_bases: bool,
+    pub ilvl_breakpoints: bool,
+    pub plan_recipes: bool,
+    pub implicits_table: bool,
+}
+
+/// Data computed for request, ui keeps only the response of its latest request
+pub struct CraftResponse {
+    pub id: u64,
+    pub data: Data,
 }
@@ -0,0 +1,197 @@
+use crate::entities::craft_repo::ModItem;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use strum_macros::EnumIter;
+
+/// Boolean description of a desired craft result.
+///
+/// Serialized as json, e.g.
+/// `{"op": "or", "exprs": [{"op": "mod", "mod_id": "IncreasedLife4"}, {"op": "open_prefixes", "count": 1}]}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TargetExpr {
+    /// target mod or any better tier of it
+    Mod {
+        mod_id: String,
+    },
+    And {
+        exprs: Vec<TargetExpr>,
+    },
+    Or {
+        exprs: Vec<TargetExpr>,
+    },
+    Not {
+        expr: Box<TargetExpr>,
+    },
+    /// at least `count` of the expressions are satisfied
+    AtLeast {
+        count: usize,
+        exprs: Vec<TargetExpr>,
+    },
+    OpenPrefixes {
+        count: usize,
+    },
+    OpenSuffixes {
+        count: usize,
+    },
+    /// aggregated value of pseudo stat is at least `min`
+    Pseudo {
+        stat: PseudoStat,
+        min: f64,
+    },
+    /// implicit or enchant is on item, chaos orb doesn't roll it,
+    /// so it is checked on pasted item only
+    Implicit {
+        mod_id: String,
+    },
+}
+
+impl TargetExpr {
+    /// Plain AND of selected mods, the default target.
+    pub fn all_of(mods: &[ModItem]) -> TargetExpr {
+        TargetExpr::And {
+            exprs: mods
+                .iter()
+                .map(|m| TargetExpr::Mod {
+                    mod_id: m.mod_key.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Mod ids referenced anywhere in the expression.
+    pub fn mod_ids(&self) -> HashSet<String> {
+        let mut res = HashSet::new();
+        self.collect_mod_ids(&mut res);
+        res
+    }
+
+    fn collect_mod_ids(&self, res: &mut HashSet<String>) {
+        match self {
+            TargetExpr::Mod { mod_id } => {
+                res.insert(mod_id.clone());
+            }
+            TargetExpr::And { exprs }
+            | TargetExpr::Or { exprs }
+            | TargetExpr::AtLeast { exprs, .. } => {
+                exprs.iter().for_each(|e| e.collect_mod_ids(res));
+            }
+            TargetExpr::Not { expr } => expr.collect_mod_ids(res),
+            TargetExpr::OpenPrefixes { .. }
+            | TargetExpr::OpenSuffixes { .. }
+            | TargetExpr::Pseudo { .. }
+            | TargetExpr::Implicit { .. } => (),
+        }
+    }
+
+    /// Whether rolled values of mods are needed to evaluate the expression.
+    pub fn uses_pseudo_stats(&self) -> bool {
+        match self {
+            TargetExpr::Pseudo { .. } => true,
+            TargetExpr::And { exprs }
+            | TargetExpr::Or { exprs }
+            | TargetExpr::AtLeast { exprs, .. } => exprs.iter().any(|e| e.uses_pseudo_stats()),
+            TargetExpr::Not { expr } => expr.uses_pseudo_stats(),
+            _ => false,
+        }
+    }
+
+    /// Whether expression checks implicits, which are known for pasted item only
+    pub fn uses_implicits(&self) -> bool {
+        match self {
+            TargetExpr::Implicit { .. } => true,
+            TargetExpr::And { exprs }
+            | TargetExpr::Or { exprs }
+            | TargetExpr::AtLeast { exprs, .. } => exprs.iter().any(|e| e.uses_implicits()),
+            TargetExpr::Not { expr } => expr.uses_implicits(),
+            _ => false,
+        }
+    }
+
+    /// Mod ids of an expression that is a plain AND of mods, None for any other shape.
+    pub fn as_plain_mods(&self) -> Option<Vec<String>> {
+        match self {
+            TargetExpr::Mod { mod_id } => Some(vec![mod_id.clone()]),
+            TargetExpr::And { exprs } => exprs
+                .iter()
+                .map(|e| match e {
+                    TargetExpr::Mod { mod_id } => Some(mod_id.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_json(raw: &str) -> Result<TargetExpr, String> {
+        serde_json::from_str(raw).map_err(|e| format!("Wrong target expression: {}", e))
+    }
+}
+
+/// How selected mods are combined in the target built from ui.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TargetMode {
+    All,
+    Any,
+    AtLeast,
+}
+
+impl TargetMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TargetMode::All => "all of",
+            TargetMode::Any => "any of",
+            TargetMode::AtLeast => "at least N of",
+        }
+    }
+}
+
+/// Stats aggregated from several mods, like trade site pseudo mods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub enum PseudoStat {
+    TotalFireResistance,
+    TotalColdResistance,
+    TotalLightningResistance,
+    TotalChaosResistance,
+    TotalElementalResistance,
+    TotalResistance,
+    TotalLife,
+    TotalMana,
+    TotalEnergyShield,
+    TotalStrength,
+    TotalDexterity,
+    TotalIntelligence,
+    TotalAttributes,
+}
+
+impl PseudoStat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PseudoStat::TotalFireResistance => "pseudo_total_fire_res",
+            PseudoStat::TotalColdResistance => "pseudo_total_cold_res",
+            PseudoStat::TotalLightningResistance => "pseudo_total_lightning_res",
+            PseudoStat::TotalChaosResistance => "pseudo_total_chaos_res",
+            PseudoStat::TotalElementalResistance => "pseudo_total_elemental_res",
+            PseudoStat::TotalResistance => "pseudo_total_res",
+            PseudoStat::TotalLife => "pseudo_total_life",
+            PseudoStat::TotalMana => "pseudo_total_mana",
+            PseudoStat::TotalEnergyShield => "pseudo_total_es",
+            PseudoStat::TotalStrength => "pseudo_total_str",
+            PseudoStat::TotalDexterity => "pseudo_total_dex",
+            PseudoStat::TotalIntelligence => "pseudo_total_int",
+            PseudoStat::TotalAttributes => "pseudo_total_attributes",
+        }
+    }
+}
+
+/// Pseudo stat condition picked in ui.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PseudoRule {
+    pub stat: PseudoStat,
+    pub min: f64,
+}
@@ -1,12 +1,14 @@
-use crate::entities::craft_repo::{BackEvents, CraftRepo, UiStates};
+use crate::entities::craft_repo::{
+    AutocraftSettings, BackEvents, CraftRepo, CurrencyPositions, UiStates,
+};
 use crate::storage::files::local_db::FileRepo;
-use crate::usecases::matcher::{check_matching, ModMatcher};
+#[cfg(target_os = "windows")]
+use crate::usecases::matcher::{check_matching, CraftedItem, ModMatcher};
 use chrono::{DateTime, Utc};
 use log::{debug, info};
 use rdev::{listen, simulate, EventType, Key};
 use std::collections::HashSet;
-use std::sync::mpsc::{channel, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread;
 use std::time::{Duration, SystemTime};
 
@@ -15,13 +17,18 @@ fn hash_event_type(event_type: EventType) -> String {
 }
 
 fn create_target_hash_set() -> HashSet<String> {
+    create_combo_hash_set(Key::KeyE)
+}
+
+/// Ctrl+Shift+<key> combo
+fn create_combo_hash_set(key: Key) -> HashSet<String> {
     let mut target_events = HashSet::new();
     // target_events.insert(hash_event_type(EventType::KeyPress(Key::ControlLeft)));
     target_events.insert(hash_event_type(EventType::KeyRelease(Key::ControlLeft)));
     // target_events.insert(hash_event_type(EventType::KeyPress(Key::ShiftLeft)));
     target_events.insert(hash_event_type(EventType::KeyRelease(Key::ShiftLeft)));
     // target_events.insert(hash_event_type(EventType::KeyPress(Key::KeyD)));
-    target_events.insert(hash_event_type(EventType::KeyRelease(Key::KeyE)));
+    target_events.insert(hash_event_type(EventType::KeyRelease(key)));
     target_events
 }
 
@@ -36,23 +43,58 @@ fn send(event_type: &EventType) {
     thread::sleep(delay);
 }
 
+/// Take currency from stash position with right click and apply it to item
+#[cfg(target_os = "windows")]
+fn apply_currency(currency_position: (f64, f64), item_position: (f64, f64)) {
+    use rdev::Button;
+
+    send(&EventType::MouseMove {
+        x: currency_position.0,
+        y: currency_position.1,
+    });
+    send(&EventType::ButtonPress(Button::Right));
+    send(&EventType::ButtonRelease(Button::Right));
+    send(&EventType::MouseMove {
+        x: item_position.0,
+        y: item_position.1,
+    });
+    send(&EventType::ButtonPress(Button::Left));
+    send(&EventType::ButtonRelease(Button::Left));
+}
+
 #[cfg(target_os = "windows")]
-fn run_craft(craft_repo: &impl CraftRepo, ui_states: Arc<Mutex<UiStates>>) -> Result<(), String> {
+fn run_craft(
+    craft_repo: &impl CraftRepo,
+    settings: &AutocraftSettings,
+    currency_positions: &CurrencyPositions,
+    item_position: Option<(f64, f64)>,
+) -> Result<(), String> {
+    use crate::entities::craft_repo::CraftMode;
     use crate::usecases::item_parser;
+    use crate::usecases::pseudo_stats::PseudoStatsCalculator;
+    use crate::usecases::rules::MAGIC_LIMITS;
     use clipboard_win::{formats, Clipboard, Getter, Setter};
     use log::error;
     use rdev::Button;
 
     println!("run crafting");
 
-    let selected_mods = ui_states.lock().unwrap().selected.clone();
-    let selected_mod_keys: HashSet<String> =
-        HashSet::from_iter(selected_mods.iter().map(|m| m.mod_key.clone()));
-    let max_tries = ui_states
-        .lock()
-        .unwrap()
-        .selected_max_autocraft_tries
-        .clone();
+    let target = settings.target.clone()?;
+    let max_tries = settings.max_tries;
+    let craft_mode = settings.craft_mode;
+    let augmentation = match craft_mode {
+        CraftMode::Chaos => None,
+        CraftMode::Alteration => {
+            let item_position = item_position.ok_or("Could not detect item position")?;
+            let alteration_position = currency_positions
+                .alteration
+                .ok_or("Set alteration position with Ctrl+Shift+Q")?;
+            let augmentation_position = currency_positions
+                .augmentation
+                .ok_or("Set augmentation position with Ctrl+Shift+A")?;
+            Some((item_position, alteration_position, augmentation_position))
+        }
+    };
     send(&EventType::KeyPress(Key::ShiftLeft));
     send(&EventType::KeyPress(Key::Alt));
 
@@ -60,6 +102,8 @@ fn run_craft(craft_repo: &impl CraftRepo, ui_states: Arc<Mutex<UiStates>>) -> Re
     let mut down_counter = max_tries;
 
     let mut no_changes_in_clipboard_counter: u32 = 0;
+    let mut matcher: Option<ModMatcher> = None;
+    let pseudo_stats_calculator = PseudoStatsCalculator::new();
     while down_counter > 0 {
         send(&EventType::KeyPress(Key::ControlLeft));
         send(&EventType::KeyPress(Key::KeyC));
@@ -98,21 +142,32 @@ fn run_craft(craft_repo: &impl CraftRepo, ui_states: Arc<Mutex<UiStates>>) -> Re
             }
         };
         println!("parsed {:#?}", &parsed_craft);
-        let crafted_mod_keys: HashSet<String> = HashSet::from_iter(parsed_craft.mods);
-        // FIXME! create mathcer only once!
-        let matcher = match ModMatcher::new(selected_mod_keys.clone(), &parsed_craft.item_base_name, craft_repo){
-            Ok(m) => m,
-            Err(e) => {
-                error!("stop crafting: {}", e);
-                send(&EventType::KeyRelease(Key::ShiftLeft));
-                send(&EventType::KeyRelease(Key::Alt));
-                output.clear();
-                break;
-            },
+        let crafted = CraftedItem {
+            pseudo_stats: pseudo_stats_calculator.calculate(&parsed_craft.raw_mods),
+            mod_ids: HashSet::from_iter(parsed_craft.mods),
+            implicit_ids: item_parser::parse_item_implicits(craft_repo, &output)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|i| i.mod_id)
+                .collect(),
         };
-        
+        if matcher.is_none() {
+            match ModMatcher::new(target.clone(), &parsed_craft.item_base_name, craft_repo) {
+                Ok(m) if craft_mode == CraftMode::Alteration => {
+                    matcher = Some(m.with_affix_limits(MAGIC_LIMITS))
+                }
+                Ok(m) => matcher = Some(m),
+                Err(e) => {
+                    error!("stop crafting: {}", e);
+                    send(&EventType::KeyRelease(Key::ShiftLeft));
+                    send(&EventType::KeyRelease(Key::Alt));
+                    output.clear();
+                    break;
+                }
+            };
+        }
 
-        if check_matching(matcher, crafted_mod_keys) {
+        if check_matching(matcher.as_ref().unwrap(), &crafted) {
             info!("Crafted all target mods successfully");
             send(&EventType::KeyRelease(Key::ShiftLeft));
             send(&EventType::KeyRelease(Key::Alt));
@@ -122,6 +177,28 @@ fn run_craft(craft_repo: &impl CraftRepo, ui_states: Arc<Mutex<UiStates>>) -> Re
 
         output.clear();
 
+        if let Some((item_position, alteration_position, augmentation_position)) = augmentation {
+            if matcher.as_ref().unwrap().worth_to_augment(&crafted) {
+                info!("Augment magic item");
+                send(&EventType::KeyRelease(Key::ShiftLeft));
+                apply_currency(augmentation_position, item_position);
+                // take alteration back, next iteration checks augmented item
+                send(&EventType::MouseMove {
+                    x: alteration_position.0,
+                    y: alteration_position.1,
+                });
+                send(&EventType::ButtonPress(Button::Right));
+                send(&EventType::ButtonRelease(Button::Right));
+                send(&EventType::KeyPress(Key::ShiftLeft));
+                send(&EventType::MouseMove {
+                    x: item_position.0,
+                    y: item_position.1,
+                });
+                down_counter -= 1;
+                continue;
+            }
+        }
+
         send(&EventType::ButtonPress(Button::Left));
         send(&EventType::ButtonRelease(Button::Left));
         down_counter -= 1;
@@ -134,11 +211,21 @@ fn run_craft(craft_repo: &impl CraftRepo, ui_states: Arc<Mutex<UiStates>>) -> Re
 }
 
 #[cfg(target_os = "linux")]
-fn run_craft(_repo: &impl CraftRepo, _ui_states: Arc<Mutex<UiStates>>) -> Result<(), String> {
+fn run_craft(
+    _repo: &impl CraftRepo,
+    _settings: &AutocraftSettings,
+    _currency_positions: &CurrencyPositions,
+    _item_position: Option<(f64, f64)>,
+) -> Result<(), String> {
     Err(String::from("Auto crafting is not supported on linux yet"))
 }
 
-pub fn run_listener_in_background(sender: Sender<BackEvents>, ui_states: Arc<Mutex<UiStates>>) {
+/// Autocraft uses the latest settings sent by ui,
+/// currency positions are kept here and sent to ui to be shown
+pub fn run_listener_in_background(
+    sender: Sender<BackEvents>,
+    settings_receiver: Receiver<AutocraftSettings>,
+) {
     // !TODO use common instance between threads
     let craft_repo: FileRepo;
     match FileRepo::new() {
@@ -170,13 +257,40 @@ pub fn run_listener_in_background(sender: Sender<BackEvents>, ui_states: Arc<Mut
         let keypress_bandwidth = Duration::from_millis(1000);
         let mut events = Vec::new();
         let target_events = create_target_hash_set();
+        let alteration_position_events = create_combo_hash_set(Key::KeyQ);
+        let augmentation_position_events = create_combo_hash_set(Key::KeyA);
         // println!("target_events {:?}", target_events);
         let mut last_combo = SystemTime::now() - Duration::from_secs(500);
+        let mut cursor_position: Option<(f64, f64)> = None;
+        let mut settings = UiStates::default().autocraft_settings();
+        let mut currency_positions = CurrencyPositions::default();
         for event in rchan.iter() {
+            if let EventType::MouseMove { x, y } = event.event_type {
+                cursor_position = Some((x, y));
+                continue;
+            }
             events.push(event);
             events.retain(|e| e.time > SystemTime::now() - keypress_bandwidth);
             let current_events =
                 HashSet::from_iter(events.iter().map(|e| hash_event_type(e.event_type)));
+            if alteration_position_events.is_subset(&current_events) {
+                info!("alteration position saved: {:?}", cursor_position);
+                currency_positions.alteration = cursor_position;
+                sender
+                    .send(BackEvents::CurrencyPositions(currency_positions.clone()))
+                    .ok();
+                events.clear();
+                continue;
+            }
+            if augmentation_position_events.is_subset(&current_events) {
+                info!("augmentation position saved: {:?}", cursor_position);
+                currency_positions.augmentation = cursor_position;
+                sender
+                    .send(BackEvents::CurrencyPositions(currency_positions.clone()))
+                    .ok();
+                events.clear();
+                continue;
+            }
             if target_events.is_subset(&current_events)
                 && last_combo < SystemTime::now() - keypress_bandwidth
             {
@@ -184,7 +298,10 @@ pub fn run_listener_in_background(sender: Sender<BackEvents>, ui_states: Arc<Mut
                 println!("You pressed combo! prev combo at {}", t.to_rfc3339());
                 last_combo = SystemTime::now();
                 events.clear();
-                match run_craft(&craft_repo, Arc::clone(&ui_states)) {
+                if let Some(latest) = settings_receiver.try_iter().last() {
+                    settings = latest;
+                }
+                match run_craft(&craft_repo, &settings, &currency_positions, cursor_position) {
                     Ok(_) => {}
                     Err(e) => {
                         sender
@@ -1,106 +1,651 @@
 // use anyhow::Result;
-use lazy_crafter::entities::craft_repo::Message;
-use lazy_crafter::entities::craft_repo::{BackEvents, Data, ModsQuery, UiEvents, UiStates};
+use lazy_crafter::entities::craft_repo::{
+    BackEvents, BaseRanking, ClusterEnchant, CraftMode, CraftRequest, CraftResponse, Data,
+    Estimation, IlvlReport, Influence, ItemBase, ItemImplicit, MagicEstimation, MetaCraft,
+    MetaCraftEstimation, ModFamily, ModItem, ModsQuery, Recipe, Reforge, ReforgeEstimation,
+    SingleActionEstimation, UiEvents,
+};
+use lazy_crafter::entities::target::TargetExpr;
 use log::{debug, error, info};
 extern crate x11_clipboard;
 
 use lazy_crafter::key_listener;
-use lazy_crafter::storage::files::local_db::FileRepo;
-use lazy_crafter::ui::ui_app;
+use lazy_crafter::storage::files::local_db::{FileRepo, DATA_DIR};
+use lazy_crafter::storage::files::watcher;
+use lazy_crafter::ui::ui_app::{self, UiContext};
+use lazy_crafter::usecases::base_comparison;
 use lazy_crafter::usecases::craft_searcher;
 use lazy_crafter::usecases::estimation;
-use lazy_crafter::utils::sync_ext::MutexLockSExt;
-use std::sync::{mpsc, Arc, Mutex};
+use lazy_crafter::usecases::ilvl_breakpoints;
+use lazy_crafter::usecases::item_parser;
+use lazy_crafter::usecases::meta_crafting;
+use lazy_crafter::usecases::mod_families;
+use lazy_crafter::usecases::mods_table;
+use lazy_crafter::usecases::single_action;
+use lazy_crafter::usecases::strategy_planner;
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 
-fn handle_event(
-    ui_states: &Arc<Mutex<UiStates>>,
-    data: &Arc<Mutex<Data>>,
-    event: UiEvents,
-    craft_repo: &FileRepo,
-) -> Result<(), String> {
-    if event == UiEvents::Started {
-        let item_classes = craft_searcher::get_item_classes(craft_repo);
-        let item_class_by_base_name = craft_searcher::get_item_class_by_item_name(craft_repo);
-        let data = &mut data.lock_s()?;
-        data.item_classes = item_classes;
-        data.item_class_by_base_name = item_class_by_base_name;
-        debug!(target: "db thread", "Loaded item classes by stat event");
+/// Item classes are loaded once and copied into every response
+struct StaticData {
+    item_classes: Vec<String>,
+    item_class_by_base_name: HashMap<String, String>,
+}
+
+/// Query parts which estimations depend on, filter text of mods table isn't one of them
+#[derive(Clone, PartialEq)]
+struct QueryKey {
+    item_base: String,
+    item_level: u64,
+    selected_mod_ids: Vec<String>,
+    influences: Vec<Influence>,
+    cluster_enchant: Option<String>,
+}
+
+impl QueryKey {
+    fn new(query: &ModsQuery) -> QueryKey {
+        QueryKey {
+            item_base: query.item_base.clone(),
+            item_level: query.item_level,
+            selected_mod_ids: query
+                .selected_mods
+                .iter()
+                .map(|m| m.mod_key.clone())
+                .collect(),
+            influences: query.influences.clone(),
+            cluster_enchant: query.cluster_enchant.clone(),
+        }
     }
-    let ui_state = ui_states.lock_s()?;
-    info!(target: "db thread", "Got event, ui_state is {:?}", ui_state);
+}
 
-    let item_class = &ui_state.selected_item_class_as_filter;
-    let item_bases = craft_searcher::get_item_bases(craft_repo, &item_class);
+/// Value computed for the last inputs
+struct Cached<K, V> {
+    last: Option<(K, V)>,
+}
 
-    let query = ModsQuery {
-        string_query: ui_state.filter_string.clone(),
-        item_base: ui_state.selected_item_base_as_filter.clone(),
-        item_level: ui_state.selected_item_level_as_filter,
-        selected_mods: ui_state.selected.clone(),
+impl<K, V> Default for Cached<K, V> {
+    fn default() -> Self {
+        Cached { last: None }
+    }
+}
+
+impl<K: PartialEq, V: Clone> Cached<K, V> {
+    /// Value is computed again only when inputs change.
+    /// None when request became stale, result of stopped computation isn't kept
+    fn get_or_compute(
+        &mut self,
+        inputs: K,
+        is_stale: &dyn Fn() -> bool,
+        compute: impl FnOnce() -> V,
+    ) -> Option<V> {
+        if let Some((last_inputs, value)) = &self.last {
+            if *last_inputs == inputs {
+                return Some(value.clone());
+            }
+        }
+        let value = compute();
+        if is_stale() {
+            return None;
+        }
+        self.last = Some((inputs, value.clone()));
+        Some(value)
+    }
+}
+
+type CachedResult<K, T> = Cached<K, Option<Result<T, String>>>;
+type Target = Result<TargetExpr, String>;
+
+/// Mods table with its pool, it changes with query and view of table only
+#[derive(Clone)]
+struct ModsView {
+    item_bases: Vec<ItemBase>,
+    cluster_enchants: Vec<ClusterEnchant>,
+    mods_table: Vec<ModItem>,
+    mod_families: Vec<ModFamily>,
+    pool_weight_by_generation_type: HashMap<String, u32>,
+    mod_tags: Vec<String>,
+    search_error: Option<String>,
+    implicit_mods: Option<Vec<ModItem>>,
+}
+
+/// Results of served requests, cache is created again with repo
+#[derive(Default)]
+struct ResponseCache {
+    mods_view: Option<ModsView>,
+    estimation: CachedResult<(QueryKey, Target), Estimation>,
+    magic_estimation: CachedResult<(QueryKey, CraftMode), MagicEstimation>,
+    reforge_estimation: CachedResult<(QueryKey, Target, Option<Reforge>), ReforgeEstimation>,
+    meta_craft_estimation: CachedResult<(QueryKey, Target, Vec<MetaCraft>), MetaCraftEstimation>,
+    single_action_estimation: CachedResult<(QueryKey, Target, String), SingleActionEstimation>,
+    recipes: CachedResult<(QueryKey, Target, Vec<MetaCraft>, bool), Vec<Recipe>>,
+    base_ranking: CachedResult<(String, QueryKey, bool), Vec<BaseRanking>>,
+    ilvl_report: CachedResult<(QueryKey, bool), IlvlReport>,
+    item_implicits: CachedResult<String, Vec<ItemImplicit>>,
+}
+
+/// Events of estimation settings keep mods table as it is,
+/// the rest change query or view of the table
+fn changes_mods_view(event: &UiEvents) -> bool {
+    !matches!(
+        event,
+        UiEvents::ChangeTarget
+            | UiEvents::ChangeCraftMode
+            | UiEvents::ChangeReforge
+            | UiEvents::ChangeMetaCrafts
+            | UiEvents::ChangeBaseComparison
+            | UiEvents::ChangeIlvlBreakpoints
+            | UiEvents::ChangeRecipePlanner
+    )
+}
+
+fn find_mods_view(request: &CraftRequest, craft_repo: &FileRepo) -> ModsView {
+    let item_bases = craft_searcher::get_item_bases(craft_repo, &request.item_class);
+    let cluster_enchants =
+        craft_searcher::get_cluster_jewel_enchants(craft_repo, &request.query.item_base);
+    let query = request_query(request, &cluster_enchants);
+    let pool_mods = craft_searcher::find_mods(
+        craft_repo,
+        &ModsQuery {
+            string_query: "".to_string(),
+            ..query.clone()
+        },
+    );
+    let pool_weight_by_generation_type = mods_table::pool_weight_by_generation_type(&pool_mods);
+    let mod_tags = mods_table::collect_tags(&pool_mods);
+    let (found_mods, search_error) = match craft_searcher::search_mods(craft_repo, &query) {
+        Ok(mods) => (mods, None),
+        Err(e) => (vec![], Some(e)),
+    };
+    let mut mods_table = mods_table::filter_mods(found_mods, &request.mods_table_filter);
+    mods_table::sort_mods(
+        &mut mods_table,
+        request.mods_table_sort,
+        request.mods_table_sort_descending,
+        &pool_weight_by_generation_type,
+    );
+    let mod_families = mod_families::group_by_family(&mods_table);
+    let implicit_mods = request
+        .implicits_table
+        .then(|| craft_searcher::find_implicit_mods(craft_repo, &query));
+    ModsView {
+        item_bases,
+        cluster_enchants,
+        mods_table,
+        mod_families,
+        pool_weight_by_generation_type,
+        mod_tags,
+        search_error,
+        implicit_mods,
+    }
+}
+
+/// Query of request, enchant of previous cluster jewel base is ignored
+fn request_query(request: &CraftRequest, cluster_enchants: &[ClusterEnchant]) -> ModsQuery {
+    ModsQuery {
+        cluster_enchant: request
+            .query
+            .cluster_enchant
+            .clone()
+            .filter(|tag| cluster_enchants.iter().any(|e| &e.tag == tag)),
+        ..request.query.clone()
+    }
+}
+
+/// Data for request, None when a newer request arrived during computation.
+/// Mods table is searched again for events which change it,
+/// estimations are computed again when their inputs change
+fn handle_request(
+    request: &CraftRequest,
+    static_data: &StaticData,
+    craft_repo: &FileRepo,
+    cache: &mut ResponseCache,
+    is_stale: impl Fn() -> bool,
+) -> Result<Option<Data>, String> {
+    info!(target: "db thread", "Got request {} for {:?}", request.id, request.events);
+    let is_stale: &dyn Fn() -> bool = &is_stale;
+    macro_rules! cached {
+        ($field:ident, $inputs:expr, $compute:expr) => {
+            match cache.$field.get_or_compute($inputs, is_stale, || $compute) {
+                Some(value) => value,
+                None => {
+                    debug!(target: "db thread", "Request {} is stale, skipped", request.id);
+                    return Ok(None);
+                }
+            }
+        };
+    }
+
+    let mods_view = match &cache.mods_view {
+        Some(view) if !request.events.iter().any(changes_mods_view) => view.clone(),
+        _ => {
+            let view = find_mods_view(request, craft_repo);
+            cache.mods_view = Some(view.clone());
+            view
+        }
     };
-    drop(ui_state);
-    let mod_items = craft_searcher::find_mods(craft_repo, &query);
-    let estimation = estimation::calculate_estimation_for_craft(craft_repo, &query);
-    let data = &mut data.lock_s()?;
-    data.item_bases = item_bases;
-    data.estimation = Some(estimation);
-    data.mods_table = mod_items;
     debug!(target: "db thread", "Loaded item bases and filtered mods");
-    Ok(())
+    let item_class = &request.item_class;
+    // estimations don't depend on filter text of mods table
+    let query = ModsQuery {
+        string_query: "".to_string(),
+        ..request_query(request, &mods_view.cluster_enchants)
+    };
+    let query_key = QueryKey::new(&query);
+    let target = &request.target;
+    let meta_crafts = &request.meta_crafts;
+    let reforge_estimation = cached!(
+        reforge_estimation,
+        (query_key.clone(), target.clone(), request.reforge.clone()),
+        request.reforge.as_ref().map(|r| {
+            target.clone().and_then(|t| {
+                estimation::calculate_reforge_estimation(craft_repo, &query, &t, r, is_stale)
+            })
+        })
+    );
+    let meta_craft_estimation = cached!(
+        meta_craft_estimation,
+        (query_key.clone(), target.clone(), meta_crafts.clone()),
+        (!meta_crafts.is_empty()).then(|| {
+            target.clone().and_then(|t| {
+                meta_crafting::calculate_meta_craft_estimation(
+                    craft_repo,
+                    &query,
+                    &t,
+                    meta_crafts,
+                    is_stale,
+                )
+            })
+        })
+    );
+    // pasted item is the current one for exalt/annul calculator
+    let single_action_estimation = cached!(
+        single_action_estimation,
+        (
+            query_key.clone(),
+            target.clone(),
+            request.item_string.clone()
+        ),
+        (!request.item_string.trim().is_empty()).then(|| {
+            let affixes = item_parser::parse_item_affixes(craft_repo, &request.item_string)?;
+            target.clone().and_then(|t| {
+                single_action::calculate_single_actions(craft_repo, &query, &t, &affixes, is_stale)
+            })
+        })
+    );
+    let recipes = cached!(
+        recipes,
+        (
+            query_key.clone(),
+            target.clone(),
+            meta_crafts.clone(),
+            request.plan_recipes
+        ),
+        request.plan_recipes.then(|| {
+            target.clone().and_then(|t| {
+                strategy_planner::plan_recipes(craft_repo, &query, &t, meta_crafts, is_stale)
+            })
+        })
+    );
+    let estimation = cached!(
+        estimation,
+        (query_key.clone(), target.clone()),
+        Some(target.clone().and_then(|t| {
+            estimation::calculate_estimation_for_target(craft_repo, &query, &t, is_stale)
+        }))
+    );
+    let magic_estimation = cached!(
+        magic_estimation,
+        (query_key.clone(), request.craft_mode),
+        match request.craft_mode {
+            CraftMode::Alteration => {
+                Some(estimation::calculate_magic_estimation(craft_repo, &query))
+            }
+            CraftMode::Chaos => None,
+        }
+    );
+    // every base is estimated, so it works only on demand
+    let base_ranking = cached!(
+        base_ranking,
+        (item_class.clone(), query_key.clone(), request.compare_bases),
+        request
+            .compare_bases
+            .then(|| base_comparison::rank_bases(craft_repo, item_class, &query))
+    );
+    let ilvl_report = cached!(
+        ilvl_report,
+        (query_key, request.ilvl_breakpoints),
+        request
+            .ilvl_breakpoints
+            .then(|| ilvl_breakpoints::analyze_item_levels(craft_repo, &query))
+    );
+    let item_implicits = cached!(
+        item_implicits,
+        request.item_string.clone(),
+        (!request.item_string.trim().is_empty())
+            .then(|| item_parser::parse_item_implicits(craft_repo, &request.item_string))
+    );
+    Ok(Some(Data {
+        mods_table: mods_view.mods_table,
+        item_classes: static_data.item_classes.clone(),
+        item_bases: mods_view.item_bases,
+        mod_families: mods_view.mod_families,
+        pool_weight_by_generation_type: mods_view.pool_weight_by_generation_type,
+        mod_tags: mods_view.mod_tags,
+        search_error: mods_view.search_error,
+        cluster_enchants: mods_view.cluster_enchants,
+        item_class_by_base_name: static_data.item_class_by_base_name.clone(),
+        estimation,
+        magic_estimation,
+        reforge_estimation,
+        base_ranking,
+        ilvl_report,
+        recipes,
+        meta_craft_estimation,
+        single_action_estimation,
+        implicit_mods: mods_view.implicit_mods,
+        item_implicits,
+    }))
 }
 
-fn run_db_in_background(
-    receiver: mpsc::Receiver<UiEvents>,
-    ui_states: Arc<Mutex<UiStates>>,
-    data: Arc<Mutex<Data>>,
-) {
-    let craft_repo: FileRepo;
-    match FileRepo::new() {
-        Ok(repo) => {
-            craft_repo = repo;
+/// Why worker stopped serving requests
+enum WorkerExit {
+    Disconnected,
+    /// request asked for reload, it is served again with new repo
+    Reload(Box<CraftRequest>),
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(text) => *text,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(text) => text.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+/// Serves requests until reload is asked or ui is gone,
+/// `handle` returns false when response can't be sent
+fn serve_requests(
+    receiver: &mpsc::Receiver<CraftRequest>,
+    pending: Option<CraftRequest>,
+    mut handle: impl FnMut(&CraftRequest) -> bool,
+) -> WorkerExit {
+    let mut pending = pending;
+    loop {
+        let request = match pending.take() {
+            Some(request) => request,
+            None => match receiver.recv() {
+                Ok(request) => request,
+                Err(_) => return WorkerExit::Disconnected,
+            },
+        };
+        // only the newest of queued requests is worth computing, events of skipped ones still apply
+        let request = receiver.try_iter().fold(request, |mut older, mut newer| {
+            older.events.append(&mut newer.events);
+            newer.events = older.events;
+            newer
+        });
+        if request.events.contains(&UiEvents::ReloadData) {
+            return WorkerExit::Reload(Box::new(request));
         }
-        Err(e) => {
-            error!(target: "db thread", "Database initialization error! {}", e);
-            let ui_states = &mut ui_states.lock().unwrap();
-            let message = Message {
-                text: format!("Database initialization error! {}", e),
-                created_at: chrono::Local::now().timestamp(),
-            };
+        if !handle(&request) {
+            return WorkerExit::Disconnected;
+        }
+    }
+}
+
+/// Request with reload, other requests can't be served without repo
+fn wait_for_reload(receiver: &mpsc::Receiver<CraftRequest>) -> Option<CraftRequest> {
+    receiver
+        .iter()
+        .find(|request| request.events.contains(&UiEvents::ReloadData))
+}
+
+/// Reload request is computed with new repo, without the event it doesn't trigger reload again
+fn after_reload(mut request: CraftRequest) -> CraftRequest {
+    request.events.retain(|e| *e != UiEvents::ReloadData);
+    request
+}
 
-            ui_states.messages.push(message);
-            return ();
+/// Worker is restarted with new repo after panic or reload request,
+/// status of backend is reported to ui
+fn run_worker<R>(
+    mut load: impl FnMut() -> Result<R, String>,
+    receiver: &mpsc::Receiver<CraftRequest>,
+    back_sender: &mpsc::Sender<BackEvents>,
+    mut handle: impl FnMut(&mut R, &CraftRequest) -> bool,
+) {
+    let mut pending = None;
+    loop {
+        back_sender.send(BackEvents::BackendLoading).ok();
+        let mut repo = match load() {
+            Ok(repo) => repo,
+            Err(text) => {
+                error!(target: "db thread", "{}", text);
+                back_sender.send(BackEvents::BackendFailed(text)).ok();
+                match wait_for_reload(receiver) {
+                    Some(request) => {
+                        pending = Some(after_reload(request));
+                        continue;
+                    }
+                    None => return,
+                }
+            }
+        };
+        info!(target: "db thread", "db started");
+        back_sender.send(BackEvents::BackendReady).ok();
+        let served = panic::catch_unwind(AssertUnwindSafe(|| {
+            serve_requests(receiver, pending.take(), |request| {
+                handle(&mut repo, request)
+            })
+        }));
+        match served {
+            Ok(WorkerExit::Disconnected) => return,
+            Ok(WorkerExit::Reload(request)) => {
+                info!(target: "db thread", "Reloading data");
+                pending = Some(after_reload(*request));
+            }
+            // request which caused panic is dropped, so restart doesn't repeat it
+            Err(payload) => {
+                let text = format!("Backend crashed, restarting: {}", panic_message(payload));
+                error!(target: "db thread", "{}", text);
+                back_sender.send(BackEvents::Error(text)).ok();
+            }
         }
     }
+}
 
-    thread::spawn(move || loop {
-        for event in &receiver {
-            match handle_event(&ui_states, &data, event, &craft_repo) {
-                Ok(_) => (),
-                Err(e) => {
-                    error!(target: "db thread", "{}", e);
-                    return;
+fn run_db_in_background(
+    receiver: mpsc::Receiver<CraftRequest>,
+    sender: mpsc::Sender<CraftResponse>,
+    back_sender: mpsc::Sender<BackEvents>,
+    latest_request_id: Arc<AtomicU64>,
+    ui_context: UiContext,
+) {
+    thread::spawn(move || {
+        let load = || {
+            let craft_repo =
+                FileRepo::new().map_err(|e| format!("Database initialization error! {}", e))?;
+            let static_data = StaticData {
+                item_classes: craft_searcher::get_item_classes(&craft_repo),
+                item_class_by_base_name: craft_searcher::get_item_class_by_item_name(&craft_repo),
+            };
+            debug!(target: "db thread", "Loaded item classes");
+            Ok((craft_repo, static_data, ResponseCache::default()))
+        };
+        let handle =
+            |(craft_repo, static_data, cache): &mut (FileRepo, StaticData, ResponseCache),
+             request: &CraftRequest| {
+                let is_stale = || latest_request_id.load(Ordering::SeqCst) != request.id;
+                match handle_request(request, static_data, craft_repo, cache, is_stale) {
+                    Ok(Some(data)) => {
+                        let sent = sender
+                            .send(CraftResponse {
+                                id: request.id,
+                                data,
+                            })
+                            .is_ok();
+                        // ui may wait for input, so it is woken up to show the response
+                        if let Some(ctx) = ui_context.get() {
+                            ctx.request_repaint();
+                        }
+                        sent
+                    }
+                    Ok(None) => true,
+                    // one failed request doesn't stop the worker
+                    Err(e) => {
+                        error!(target: "db thread", "{}", e);
+                        back_sender.send(BackEvents::Error(e)).ok();
+                        true
+                    }
                 }
             };
-        }
+        run_worker(load, &receiver, &back_sender, handle);
     });
-    info!("db started");
+}
+
+/// Prints issues of data files as json, exit code is 1 when there are any
+fn check_data() -> i32 {
+    let craft_repo = match FileRepo::new() {
+        Ok(repo) => repo,
+        Err(e) => {
+            error!("Database initialization error! {:#}", e);
+            return 2;
+        }
+    };
+    let issues = craft_repo.check_integrity();
+    let report = serde_json::json!({ "count": issues.len(), "issues": issues });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).unwrap_or_default()
+    );
+    match issues.is_empty() {
+        true => 0,
+        false => 1,
+    }
 }
 
 fn main() {
     // ui works in main tread
-    // db loader works in another thread and wait events from main tread
+    // db loader works in another thread, it gets requests from main thread and sends responses back
     env_logger::init();
+    if std::env::args().nth(1).as_deref() == Some("check-data") {
+        std::process::exit(check_data());
+    }
     info!("Start app");
-    let (ui_tx, ui_rx): (mpsc::Sender<UiEvents>, mpsc::Receiver<UiEvents>) = mpsc::channel();
+    let (request_tx, request_rx): (mpsc::Sender<CraftRequest>, mpsc::Receiver<CraftRequest>) =
+        mpsc::channel();
+    let (response_tx, response_rx): (mpsc::Sender<CraftResponse>, mpsc::Receiver<CraftResponse>) =
+        mpsc::channel();
     let (back_tx, back_rx): (mpsc::Sender<BackEvents>, mpsc::Receiver<BackEvents>) =
         mpsc::channel();
 
-    let data = Arc::new(Mutex::new(Data::default()));
-    let ui_states = Arc::new(Mutex::new(UiStates::default()));
+    let latest_request_id = Arc::new(AtomicU64::new(0));
+    let (autocraft_tx, autocraft_rx) = mpsc::channel();
+    let ui_context = UiContext::default();
 
-    run_db_in_background(ui_rx, Arc::clone(&ui_states), Arc::clone(&data));
-    key_listener::run_listener_in_background(back_tx, Arc::clone(&ui_states));
+    run_db_in_background(
+        request_rx,
+        response_tx,
+        back_tx.clone(),
+        Arc::clone(&latest_request_id),
+        Arc::clone(&ui_context),
+    );
+    watcher::run_watcher_in_background(DATA_DIR, back_tx.clone());
+    key_listener::run_listener_in_background(back_tx, autocraft_rx);
     info!("start ui");
-    ui_app::run_ui_in_main_thread(ui_tx, back_rx, ui_states, data);
+    ui_app::run_ui_in_main_thread(
+        request_tx,
+        response_rx,
+        latest_request_id,
+        back_rx,
+        autocraft_tx,
+        ui_context,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lazy_crafter::entities::craft_repo::UiStates;
+    use rstest::rstest;
+    use std::time::Duration;
+
+    #[rstest]
+    fn test_reload_request_reloads_repo_once() {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (back_tx, _back_rx) = mpsc::channel();
+        let (handled_tx, handled_rx) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            let mut loads = 0;
+            run_worker(
+                || {
+                    loads += 1;
+                    Ok(())
+                },
+                &request_rx,
+                &back_tx,
+                |_, request| handled_tx.send(request.id).is_ok(),
+            );
+            loads
+        });
+        let reload = UiStates::default().to_request(1, vec![UiEvents::ReloadData]);
+        request_tx.send(reload).unwrap();
+        // reload request is computed with new repo instead of reloading again
+        assert_eq!(handled_rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+        drop(request_tx);
+        assert_eq!(worker.join().unwrap(), 2);
+    }
+
+    #[rstest]
+    fn test_queued_requests_keep_events() {
+        let (request_tx, request_rx) = mpsc::channel();
+        let states = UiStates::default();
+        request_tx
+            .send(states.to_request(1, vec![UiEvents::AddToSelectedMods]))
+            .unwrap();
+        request_tx
+            .send(states.to_request(2, vec![UiEvents::ChangeTarget]))
+            .unwrap();
+        drop(request_tx);
+        let mut handled = vec![];
+        serve_requests(&request_rx, None, |request| {
+            handled.push((request.id, request.events.clone()));
+            true
+        });
+        // mods table is searched again though the latest request changes target only
+        assert_eq!(
+            handled,
+            vec![(2, vec![UiEvents::AddToSelectedMods, UiEvents::ChangeTarget])]
+        );
+    }
+
+    #[rstest]
+    fn test_cached_value_of_stale_request_is_not_kept() {
+        let mut cached: Cached<u32, u32> = Cached::default();
+        let mut computed = 0;
+        assert_eq!(cached.get_or_compute(1, &|| true, || 10), None);
+        let mut compute = |v| {
+            computed += 1;
+            v
+        };
+        assert_eq!(
+            cached.get_or_compute(1, &|| false, || compute(10)),
+            Some(10)
+        );
+        assert_eq!(
+            cached.get_or_compute(1, &|| false, || compute(11)),
+            Some(10)
+        );
+        assert_eq!(
+            cached.get_or_compute(2, &|| false, || compute(20)),
+            Some(20)
+        );
+        assert_eq!(computed, 2);
+    }
 }
@@ -1,3 +1,5 @@
+pub mod integrity;
 pub mod local_db;
 mod representation;
 mod schemas;
+pub mod watcher;
@@ -0,0 +1,252 @@
+use crate::entities::craft_repo::Influence;
+use crate::storage::files::local_db::{LocalDB, ITEM_DOMAINS};
+use crate::storage::files::schemas::Mod;
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Inconsistency of RePoE data, serialized as json, e.g.
+/// `{"kind": "unknown_spawn_tag", "mod_id": "IncreasedLife1", "tag": "ring"}`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// positive spawn weight for tag which no base has
+    UnknownSpawnTag {
+        mod_id: String,
+        tag: String,
+    },
+    /// stats of craftable mod can't be translated into english
+    MissingTranslation {
+        mod_id: String,
+    },
+    MissingRequirements {
+        item_base: String,
+    },
+    /// PoB representation of mod which isn't in mods.min.json
+    UnknownRepresentationKey {
+        mod_id: String,
+    },
+    /// mods which can roll on the same base with the same text
+    DuplicateRepresentation {
+        representation: String,
+        mod_ids: Vec<String>,
+    },
+}
+
+/// Prefixes and suffixes of bases available for crafting
+pub fn is_craftable(m: &Mod) -> bool {
+    ITEM_DOMAINS.contains(&m.domain.as_str())
+        && ["prefix", "suffix"].contains(&m.generation_type.as_str())
+        && !m.stats.is_empty()
+}
+
+fn spawn_tags(m: &Mod) -> HashSet<&str> {
+    m.spawn_weights
+        .iter()
+        .filter(|sw| sw.weight > 0)
+        .map(|sw| sw.tag.as_str())
+        .collect()
+}
+
+pub fn unknown_spawn_tags(db: &LocalDB) -> Vec<IntegrityIssue> {
+    let mut known_tags: HashSet<String> = db
+        .base_items_by_name
+        .values()
+        .flat_map(|b| b.tags.iter().cloned())
+        .collect();
+    let influence_tags: Vec<String> = known_tags
+        .iter()
+        .flat_map(|t| Influence::ALL.iter().map(|i| i.tag_for(t)))
+        .collect();
+    known_tags.extend(influence_tags);
+    known_tags.extend(
+        db.cluster_jewels_by_name
+            .values()
+            .flat_map(|j| j.passive_skills.iter().map(|s| s.tag.clone())),
+    );
+    db.mods
+        .iter()
+        .filter(|(_, m)| is_craftable(m))
+        .flat_map(|(mod_id, m)| {
+            spawn_tags(m)
+                .into_iter()
+                .filter(|tag| !known_tags.contains(*tag))
+                .map(|tag| IntegrityIssue::UnknownSpawnTag {
+                    mod_id: mod_id.clone(),
+                    tag: tag.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+pub fn missing_requirements(db: &LocalDB) -> Vec<IntegrityIssue> {
+    db.base_items_by_name
+        .values()
+        .filter(|b| {
+            ITEM_DOMAINS.contains(&b.domain.as_str())
+                && b.release_state == "released"
+                && b.requirements.is_none()
+        })
+        .map(|b| IntegrityIssue::MissingRequirements {
+            item_base: b.name.clone(),
+        })
+        .collect()
+}
+
+pub fn unknown_representation_keys(db: &LocalDB) -> Vec<IntegrityIssue> {
+    db.representation_by_mod_id
+        .keys()
+        .filter(|mod_id| !db.mods.contains_key(*mod_id))
+        .map(|mod_id| IntegrityIssue::UnknownRepresentationKey {
+            mod_id: mod_id.clone(),
+        })
+        .collect()
+}
+
+/// Same text is ambiguous only for mods of one domain and affix type sharing a spawn tag
+pub fn duplicate_representations(db: &LocalDB) -> Vec<IntegrityIssue> {
+    db.representation_by_mod_id
+        .iter()
+        .filter_map(|(mod_id, repr)| db.mods.get(mod_id).map(|m| (mod_id, repr, m)))
+        .filter(|(_, _, m)| is_craftable(m))
+        .into_group_map_by(|(_, repr, m)| {
+            (
+                repr.to_string(),
+                m.domain.clone(),
+                m.generation_type.clone(),
+            )
+        })
+        .into_iter()
+        .filter_map(|((representation, _, _), mods)| {
+            let mod_ids: Vec<String> = mods
+                .iter()
+                .filter(|(a_id, _, a)| {
+                    mods.iter().any(|(b_id, _, b)| {
+                        a_id != b_id && !spawn_tags(a).is_disjoint(&spawn_tags(b))
+                    })
+                })
+                .map(|(mod_id, _, _)| mod_id.to_string())
+                .sorted()
+                .collect();
+            (!mod_ids.is_empty()).then_some(IntegrityIssue::DuplicateRepresentation {
+                representation,
+                mod_ids,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::files::schemas::{ItemBaseRich, SpawnWeight, Stat};
+    use rstest::rstest;
+    use std::collections::HashMap;
+
+    fn craftable_mod(generation_type: &str, tag: &str) -> Mod {
+        Mod {
+            domain: "item".to_string(),
+            generation_type: generation_type.to_string(),
+            spawn_weights: vec![SpawnWeight {
+                tag: tag.to_string(),
+                weight: 1000,
+            }],
+            stats: vec![Stat::default()],
+            ..Mod::default()
+        }
+    }
+
+    fn db() -> LocalDB {
+        let ring = ItemBaseRich {
+            name: "Iron Ring".to_string(),
+            item_class: "Ring".to_string(),
+            tags: vec!["ring".to_string()],
+            domain: "item".to_string(),
+            release_state: "released".to_string(),
+            requirements: None,
+            implicits: vec![],
+        };
+        LocalDB {
+            translations_by_stat_id: HashMap::new(),
+            mods: HashMap::from([
+                (
+                    "IncreasedLife1".to_string(),
+                    craftable_mod("prefix", "ring"),
+                ),
+                (
+                    "IncreasedLifeRing1".to_string(),
+                    craftable_mod("prefix", "ring"),
+                ),
+                (
+                    "IncreasedLifeBelt1".to_string(),
+                    craftable_mod("prefix", "belt"),
+                ),
+                (
+                    "ColdResist1".to_string(),
+                    craftable_mod("suffix", "ring_shaper"),
+                ),
+            ]),
+            representation_by_mod_id: HashMap::from([
+                (
+                    "IncreasedLife1".to_string(),
+                    "+(3-9) to maximum Life".to_string(),
+                ),
+                (
+                    "IncreasedLifeRing1".to_string(),
+                    "+(3-9) to maximum Life".to_string(),
+                ),
+                (
+                    "IncreasedLifeBelt1".to_string(),
+                    "+(3-9) to maximum Life".to_string(),
+                ),
+                ("RemovedMod".to_string(), "+1 to Level of Gems".to_string()),
+            ]),
+            base_items_by_name: HashMap::from([(ring.name.clone(), ring)]),
+            item_classes: HashSet::new(),
+            mod_id_by_tags: HashMap::new(),
+            cluster_jewels_by_name: HashMap::new(),
+            notable_name_by_stat_id: HashMap::new(),
+            bench_options: vec![],
+        }
+    }
+
+    #[rstest]
+    fn test_unknown_spawn_tags() {
+        assert_eq!(
+            unknown_spawn_tags(&db()),
+            vec![IntegrityIssue::UnknownSpawnTag {
+                mod_id: "IncreasedLifeBelt1".to_string(),
+                tag: "belt".to_string(),
+            }]
+        );
+    }
+
+    #[rstest]
+    fn test_base_and_representation_issues() {
+        let db = db();
+        assert_eq!(
+            missing_requirements(&db),
+            vec![IntegrityIssue::MissingRequirements {
+                item_base: "Iron Ring".to_string(),
+            }]
+        );
+        assert_eq!(
+            unknown_representation_keys(&db),
+            vec![IntegrityIssue::UnknownRepresentationKey {
+                mod_id: "RemovedMod".to_string(),
+            }]
+        );
+        // belt mod has the same text, but can't roll on ring
+        assert_eq!(
+            duplicate_representations(&db),
+            vec![IntegrityIssue::DuplicateRepresentation {
+                representation: "+(3-9) to maximum Life".to_string(),
+                mod_ids: vec![
+                    "IncreasedLife1".to_string(),
+                    "IncreasedLifeRing1".to_string()
+                ],
+            }]
+        );
+    }
+}
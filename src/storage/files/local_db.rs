@@ -1,14 +1,35 @@
-use crate::entities::craft_repo::{CraftRepo, ItemBase, ModItem, ModsQuery};
+use crate::entities::craft_repo::{
+    BenchCraft, ClusterEnchant, CraftRepo, CurrencyCost, ImplicitKind, Influence, ItemBase,
+    ItemCategory, ModHint, ModItem, ModResolution, ModsQuery,
+};
+use crate::storage::files::integrity::{self, IntegrityIssue};
 use crate::storage::files::representation::handle_stat_value;
-use crate::storage::files::schemas::{ItemBaseRich, Mod, Stat, StatTranslation};
+use crate::storage::files::schemas::{
+    ClusterJewel, ClusterJewelNotable, CraftingBenchOption, ItemBaseRich, Mod, ModOverride,
+    Stat, StatTranslation,
+};
 use anyhow::{bail, Error, Result, Context};
 use itertools::Itertools;
 use log::{debug, error};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
 
 const LOG_TARGET: &str = "file_db";
+/// repo is loaded from files of the directory, their changes reload it
+pub const DATA_DIR: &str = "data";
+/// local corrections of mods, merged over RePoE data, the file is optional
+pub const OVERRIDES_PATH: &str = "data/mods_overrides.json";
+// domains of bases available for crafting
+pub(crate) const ITEM_DOMAINS: &[&str] = &[
+    "item",
+    "heist_npc",
+    "affliction_jewel",
+    "flask",
+    "misc",
+    "abyss_jewel",
+];
 
 fn load_from_json<T>(path: &str) -> Result<Vec<T>, Error>
 where
@@ -32,6 +53,109 @@ where
     serde_json::from_str(&contents).map_err(Error::from).with_context(|| format!("Wrong file's format {}", path))
 }
 
+fn load_overrides(path: &str) -> Result<HashMap<String, ModOverride>> {
+    if !Path::new(path).exists() {
+        return Ok(HashMap::new());
+    }
+    json_to_hashmap(path)
+}
+
+/// Overrides of unknown mods are skipped with error in log
+fn apply_overrides(
+    mods: &mut HashMap<String, Mod>,
+    representation_by_mod_id: &mut HashMap<String, String>,
+    overrides: HashMap<String, ModOverride>,
+) {
+    for (mod_id, o) in overrides {
+        let m = match mods.get_mut(&mod_id) {
+            Some(m) => m,
+            None => {
+                error!(target: LOG_TARGET, "Override of unknown mod {}", mod_id);
+                continue;
+            }
+        };
+        if let Some(weight) = o.weight {
+            m.spawn_weights
+                .iter_mut()
+                .filter(|sw| sw.weight > 0)
+                .for_each(|sw| sw.weight = weight);
+        }
+        if let Some(representation) = o.representation {
+            representation_by_mod_id.insert(mod_id, representation);
+        }
+    }
+}
+
+/// Influences mod spawns with, empty for mods of plain base.
+/// Better tiers of influence mod are looked up on base with the same influences only
+fn spawn_influences(m: &Mod) -> Vec<Influence> {
+    m.spawn_weights
+        .iter()
+        .filter(|sw| sw.weight > 0)
+        .filter_map(|sw| Influence::from_tag(&sw.tag))
+        .unique()
+        .collect()
+}
+
+/// Mod with its name from RePoE
+type Candidate = (ModItem, String);
+
+/// Mods with the same text are narrowed by header details one by one,
+/// a detail which leaves no mods is ignored, e.g. tier of outdated data.
+/// Mods of plain base win over influence mods at the end
+fn narrow_candidates(candidates: Vec<Candidate>, hint: &ModHint) -> Vec<ModItem> {
+    let filters: [&dyn Fn(&Candidate) -> bool; 4] = [
+        &|(m, _)| match &hint.generation_type {
+            Some(gen_type) => &m.generation_type == gen_type,
+            None => true,
+        },
+        &|(_, name)| match &hint.name {
+            Some(hint_name) => name == hint_name,
+            None => true,
+        },
+        &|(m, _)| match hint.tier {
+            Some(tier) => m.tier == tier,
+            None => true,
+        },
+        &|(m, _)| m.influence.is_none(),
+    ];
+    let mut candidates = candidates;
+    for filter in filters.iter() {
+        if candidates.len() < 2 {
+            break;
+        }
+        let narrowed: Vec<Candidate> = candidates.iter().filter(|c| filter(c)).cloned().collect();
+        if !narrowed.is_empty() {
+            candidates = narrowed;
+        }
+    }
+    candidates.into_iter().map(|(m, _)| m).collect()
+}
+
+/// (name, generation type, tier) of mod header, all of them are required
+type HeaderKey = (String, String, usize);
+
+fn header_key(hint: &ModHint) -> Option<HeaderKey> {
+    Some((
+        hint.name.clone()?,
+        hint.generation_type.clone()?,
+        hint.tier?,
+    ))
+}
+
+/// Mod ids of base's pool by header details, a key with several ids can't resolve mod
+fn header_index(pool: &[Candidate]) -> HashMap<HeaderKey, Vec<String>> {
+    pool.iter()
+        .filter(|(_, name)| !name.is_empty())
+        .map(|(m, name)| {
+            (
+                (name.clone(), m.generation_type.clone(), m.tier),
+                m.mod_key.clone(),
+            )
+        })
+        .into_group_map()
+}
+
 pub struct LocalDB {
     pub translations_by_stat_id: HashMap<String, StatTranslation>,
     pub mods: HashMap<String, Mod>,
@@ -40,6 +164,9 @@ pub struct LocalDB {
     pub base_items_by_name: HashMap<String, ItemBaseRich>,
     pub item_classes: HashSet<String>,
     pub mod_id_by_tags: HashMap<String, Vec<String>>,
+    pub cluster_jewels_by_name: HashMap<String, ClusterJewel>,
+    pub notable_name_by_stat_id: HashMap<String, String>,
+    pub bench_options: Vec<CraftingBenchOption>,
 }
 
 pub struct FileRepo {
@@ -56,7 +183,14 @@ impl FileRepo {
             }
         }
 
-        let mods: HashMap<String, Mod> = json_to_hashmap("data/mods.min.json")?;
+        let mut mods: HashMap<String, Mod> = json_to_hashmap("data/mods.min.json")?;
+        let mut representation_by_mod_id: HashMap<String, String> =
+            json_to_hashmap("data/mods_representation_pob.json")?;
+        apply_overrides(
+            &mut mods,
+            &mut representation_by_mod_id,
+            load_overrides(OVERRIDES_PATH)?,
+        );
         let raw_base_items: HashMap<String, ItemBaseRich> =
             json_to_hashmap("data/base_items.min.json")?;
         let base_items_by_name: HashMap<String, ItemBaseRich> = raw_base_items
@@ -66,18 +200,44 @@ impl FileRepo {
         let item_classes = HashSet::from_iter(
             raw_base_items
                 .iter()
-                .filter(|(_k, v)| v.domain == "item" || v.domain == "heist_npc")
+                .filter(|(_k, v)| ITEM_DOMAINS.contains(&v.domain.as_str()))
                 .map(|(_k, v)| v.item_class.clone()),
         );
 
-        let all_tags: HashSet<String> = HashSet::from_iter(
+        let mut all_tags: HashSet<String> = HashSet::from_iter(
             raw_base_items
                 .values()
                 .filter(|b| {
-                    (b.domain == "item" || b.domain == "heist_npc") && b.release_state == "released"
+                    ITEM_DOMAINS.contains(&b.domain.as_str()) && b.release_state == "released"
                 })
                 .flat_map(|b| b.tags.clone()),
         );
+        // influenced bases get synthetic tags, e.g. "helmet_shaper"
+        let influence_tags: Vec<String> = all_tags
+            .iter()
+            .flat_map(|t| Influence::ALL.iter().map(|i| i.tag_for(t)))
+            .collect();
+        all_tags.extend(influence_tags);
+        let cluster_jewels: HashMap<String, ClusterJewel> =
+            json_to_hashmap("data/cluster_jewels.min.json")?;
+        let cluster_jewels_by_name: HashMap<String, ClusterJewel> = cluster_jewels
+            .into_values()
+            .map(|j| (j.name.clone(), j))
+            .collect();
+        // enchant tags of cluster jewels open notables and small passive mods
+        all_tags.extend(
+            cluster_jewels_by_name
+                .values()
+                .flat_map(|j| j.passive_skills.iter().map(|s| s.tag.clone())),
+        );
+        let notables: Vec<ClusterJewelNotable> =
+            load_from_json("data/cluster_jewel_notables.min.json")?;
+        let notable_name_by_stat_id = notables
+            .into_iter()
+            .map(|n| (n.jewel_stat, n.name))
+            .collect();
+        let bench_options: Vec<CraftingBenchOption> =
+            load_from_json("data/crafting_bench_options.min.json")?;
         let mut mod_id_by_tags: HashMap<String, Vec<String>> = HashMap::new();
         mods.iter().for_each(|(mod_id, m)| {
             m.spawn_weights.iter().for_each(|sw| {
@@ -91,8 +251,6 @@ impl FileRepo {
                 }
             })
         });
-        let representation_by_mod_id: HashMap<String, String> =
-            json_to_hashmap("data/mods_representation_pob.json")?;
         debug!(target: LOG_TARGET, "tags: {:?}", mod_id_by_tags.keys());
         Ok(Self {
             db: LocalDB {
@@ -103,10 +261,133 @@ impl FileRepo {
                 base_items_by_name,
                 item_classes,
                 mod_id_by_tags,
+                cluster_jewels_by_name,
+                notable_name_by_stat_id,
+                bench_options,
             },
         })
     }
 
+    /// Mods which can be on item base with their names from RePoE
+    fn named_pool(&self, item_name: &str) -> Vec<Candidate> {
+        let query = ModsQuery {
+            item_base: item_name.to_string(),
+            item_level: 100,
+            string_query: "".to_string(),
+            selected_mods: vec![],
+            influences: vec![],
+            cluster_enchant: None,
+        };
+        let mut mods = self.find_mods(&query);
+        // influence mods are checked after mods of plain base
+        let influenced_query = ModsQuery {
+            influences: Influence::ALL.to_vec(),
+            ..query.clone()
+        };
+        mods.extend(
+            self.find_mods(&influenced_query)
+                .into_iter()
+                .filter(|m| m.influence.is_some()),
+        );
+        // as well as mods opened by cluster jewel enchants
+        let known_mod_keys: HashSet<String> = mods.iter().map(|m| m.mod_key.clone()).collect();
+        let enchanted_mods = self
+            .get_cluster_jewel_enchants(item_name)
+            .into_iter()
+            .flat_map(|e| {
+                self.find_mods(&ModsQuery {
+                    cluster_enchant: Some(e.tag),
+                    ..query.clone()
+                })
+            })
+            .filter(|m| !known_mod_keys.contains(&m.mod_key))
+            .collect::<Vec<ModItem>>();
+        mods.extend(enchanted_mods);
+        mods.into_iter()
+            .unique_by(|m| m.mod_key.clone())
+            .map(|m| {
+                let name = self
+                    .get_mod_by_id(&m.mod_key)
+                    .map(|d| d.name.clone())
+                    .unwrap_or_default();
+                (m, name)
+            })
+            .collect()
+    }
+
+    // parse raw mod string to mod keys
+    // provided raw mod string and each available mod for item_base to common template
+    // Idea: bring mod_name to mods representation in db and equal it
+    fn mods_by_text(pool: &[Candidate], mod_name: &str) -> Vec<Candidate> {
+        use regex::Regex;
+
+        //  bring input mod text in representation form
+        //  "blalba +4(2-9) blabla" to "blalba +(2-9) blabla"
+
+        let mod_template = Regex::new(r#"([+-])?(\d+(\.\d+)?)(\([aA-zZ]*)"#)
+            .unwrap()
+            .replace_all(mod_name.trim(), "$1$4");
+
+        let multiline_mod = mod_template.contains("\n");
+        pool.iter()
+            .filter(|(m, _)| match multiline_mod {
+                // trivial case, check input mod is equal representation
+                false => &mod_template == &m.representation,
+                // complex case, order of lines may be different for input mod and representation
+                true => {
+                    // cut unequal with cheap operations
+                    match (&m.representation.len() == &mod_template.len())
+                        && m.representation.contains("\n")
+                    {
+                        // compare mods as sorted lines
+                        true => {
+                            let mut v1 = m.representation.split("\n").collect::<Vec<&str>>();
+                            let mut v2 = mod_template.split("\n").collect::<Vec<&str>>();
+                            v1.sort();
+                            v2.sort();
+                            v1 == v2
+                        }
+                        false => false,
+                    }
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn text_to_mod(pool: &[Candidate], mod_name: &str, hint: &ModHint) -> Result<String, String> {
+        let mut mods = narrow_candidates(Self::mods_by_text(pool, mod_name), hint);
+        match mods.len() {
+            0 => Err(format!("Can't find mod {}", mod_name)),
+            1 => Ok(mods.remove(0).mod_key),
+            _ => Err(format!(
+                "Ambiguous mod {}: {}",
+                mod_name,
+                mods.iter().map(|m| m.mod_key.as_str()).sorted().join(", ")
+            )),
+        }
+    }
+
+    /// Inconsistencies of loaded data, sorted to make reports comparable
+    pub fn check_integrity(&self) -> Vec<IntegrityIssue> {
+        let mut issues = integrity::unknown_spawn_tags(&self.db);
+        issues.extend(
+            self.db
+                .mods
+                .iter()
+                .filter(|(_, m)| integrity::is_craftable(m))
+                .filter(|(_, m)| self.get_mods_representation(m).is_err())
+                .map(|(mod_id, _)| IntegrityIssue::MissingTranslation {
+                    mod_id: mod_id.clone(),
+                }),
+        );
+        issues.extend(integrity::missing_requirements(&self.db));
+        issues.extend(integrity::unknown_representation_keys(&self.db));
+        issues.extend(integrity::duplicate_representations(&self.db));
+        issues.sort();
+        issues
+    }
+
     fn get_mod_by_id(&self, mod_id: &str) -> Option<&Mod> {
         self.db.mods.get(mod_id)
     }
@@ -118,6 +399,44 @@ impl FileRepo {
             .find(|i| i.name == item_base)
     }
 
+    /// Item base with synthetic tags of influences
+    fn influenced_item(&self, item: &ItemBaseRich, influences: &[Influence]) -> ItemBaseRich {
+        let mut influenced = item.clone();
+        for influence in influences {
+            influenced
+                .tags
+                .extend(item.tags.iter().map(|t| influence.tag_for(t)));
+        }
+        influenced
+    }
+
+    /// Item base with synthetic tags of query's influences and cluster jewel enchant
+    fn item_for_query(&self, item: &ItemBaseRich, query: &ModsQuery) -> ItemBaseRich {
+        let mut res = self.influenced_item(item, &query.influences);
+        if let Some(enchant) = &query.cluster_enchant {
+            res.tags.push(enchant.clone());
+        }
+        res
+    }
+
+    /// Name of cluster jewel notable granted by mod
+    fn notable_name(&self, m: &Mod) -> Option<&String> {
+        m.stats
+            .iter()
+            .find_map(|s| self.db.notable_name_by_stat_id.get(&s.id))
+    }
+
+    /// Mods added by crafting bench options of item class
+    fn get_bench_mod_ids(&self, item_class: &str) -> HashSet<String> {
+        self.db
+            .bench_options
+            .iter()
+            .filter(|o| o.item_classes.iter().any(|c| c == item_class))
+            .filter_map(|o| o.actions.add_explicit_mod.clone())
+            .filter(|mod_id| self.get_mod_by_id(mod_id).is_some())
+            .collect()
+    }
+
     fn get_mod_ids_for_item(&self, item: &ItemBaseRich) -> HashSet<String> {
         let mut mod_ids_to_check: HashSet<String> = HashSet::new();
         for t in &item.tags {
@@ -290,6 +609,86 @@ impl FileRepo {
         Ok(reprs.join("\n").to_string())
     }
 
+    /// Tiers of mods within families on item, highest required level is tier 1
+    fn tier_by_mod_id(&self, mod_ids: &HashSet<String>, item: &ItemBaseRich) -> HashMap<String, usize> {
+        let target_gen_types = ["suffix", "prefix"];
+        mod_ids
+            .iter()
+            .map(|m_id| (m_id, self.get_mod_by_id(m_id).unwrap()))
+            .filter(|(_, m)| {
+                !m.stats.is_empty()
+                    && m.domain == item.domain
+                    && target_gen_types.contains(&m.generation_type.as_str())
+            })
+            .into_group_map_by(|(_, m)| (m.generation_type.clone(), m.type_field.clone()))
+            .into_values()
+            .flat_map(|family| {
+                family
+                    .into_iter()
+                    .sorted_by(|(a_id, a), (b_id, b)| {
+                        b.required_level.cmp(&a.required_level).then(a_id.cmp(b_id))
+                    })
+                    .enumerate()
+                    .map(|(i, (m_id, _))| (m_id.clone(), i + 1))
+            })
+            .collect()
+    }
+
+    fn mod_representation(&self, m_id: &str, m: &Mod) -> String {
+        self.get_mods_representation_pob_source(m_id)
+            .or_else(|e| match self.notable_name(m) {
+                Some(name) => Ok(format!("1 Added Passive Skill is {}", name)),
+                None => Err(e),
+            })
+            .or_else(|e| match m.domain == "affliction_jewel" {
+                true => self.get_mods_representation(m).map_err(|_| e),
+                false => Err(e),
+            })
+            .unwrap_or_else(|_| format!("representation_err: {}", m_id))
+    }
+
+    /// Mods of other kinds than affixes, generation type of base's implicits is "implicit"
+    fn create_implicit_items(&self, item: &ItemBaseRich, max_item_level: u64) -> Vec<ModItem> {
+        let base_implicits = item
+            .implicits
+            .iter()
+            .filter_map(|m_id| self.get_mod_by_id(m_id).map(|m| (m_id, m, "implicit", 0)));
+        let spawned = self.get_mod_ids_for_item(item).into_iter().filter_map(|m_id| {
+            let (m_id, m) = self.db.mods.get_key_value(&m_id)?;
+            if ImplicitKind::from_generation_type(&m.generation_type).is_none()
+                || m.domain != item.domain
+                || m.required_level > max_item_level
+            {
+                return None;
+            }
+            let weight = m
+                .spawn_weights
+                .iter()
+                .find(|sw| sw.weight > 0 && item.tags.contains(&sw.tag))?
+                .weight;
+            Some((m_id, m, m.generation_type.as_str(), weight))
+        });
+        base_implicits
+            .chain(spawned)
+            .filter(|(_, m, _, _)| !m.stats.is_empty())
+            .map(|(m_id, m, generation_type, weight)| ModItem {
+                required_level: m.required_level,
+                generation_type: generation_type.to_string(),
+                weight,
+                representation: self.mod_representation(m_id, m),
+                mod_key: m_id.clone(),
+                influence: None,
+                family: m.type_field.clone(),
+                tags: m.implicit_tags.clone(),
+                groups: m.groups.clone(),
+                tier: 1,
+                domain: m.domain.clone(),
+                stat_ids: m.stats.iter().map(|s| s.id.clone()).collect(),
+            })
+            .unique_by(|m| m.mod_key.clone())
+            .collect()
+    }
+
     fn create_mod_items(
         &self,
         mod_ids: &HashSet<String>,
@@ -298,6 +697,7 @@ impl FileRepo {
         max_item_level: u64,
     ) -> Vec<ModItem> {
         let target_gen_types = ["suffix", "prefix"];
+        let tier_by_mod_id = self.tier_by_mod_id(mod_ids, item);
         let mut res = vec![];
         for m_id in mod_ids {
             let m = self.get_mod_by_id(m_id).unwrap();
@@ -310,33 +710,38 @@ impl FileRepo {
             {
                 continue;
             }
+            let spawn_weight = m
+                .spawn_weights
+                .iter()
+                .filter(|sw| sw.weight > 0 && item.tags.contains(&sw.tag))
+                .next()
+                .unwrap();
             let mod_item = ModItem {
                 required_level: m.required_level,
                 generation_type: m.generation_type.clone(),
-                weight: m
-                    .spawn_weights
-                    .iter()
-                    .filter(|sw| sw.weight > 0 && item.tags.contains(&sw.tag))
-                    .next()
-                    .unwrap()
-                    .weight,
-                representation: self
-                    .get_mods_representation_pob_source(m_id)
-                    .unwrap_or_else(|_| format!("representation_err: {}", m_id)),
+                weight: spawn_weight.weight,
+                representation: self.mod_representation(m_id, m),
                 mod_key: m_id.clone(),
+                influence: Influence::from_tag(&spawn_weight.tag),
+                family: m.type_field.clone(),
+                tags: m.implicit_tags.clone(),
+                groups: m.groups.clone(),
+                tier: tier_by_mod_id.get(m_id).copied().unwrap_or(1),
+                domain: m.domain.clone(),
+                stat_ids: m.stats.iter().map(|s| s.id.clone()).collect(),
             };
             res.push(mod_item);
         }
         res
     }
 
-    fn get_weight_of_target_and_better_mods(
+    fn find_target_and_better_mods(
         &self,
         mod_ids: &HashSet<String>,
         item: &ItemBaseRich,
         target_mod_key: &str,
         max_item_level: u64,
-    ) -> u32 {
+    ) -> Vec<(String, u32)> {
         let target_gen_types = ["suffix", "prefix"];
         let mut res = vec![];
 
@@ -374,9 +779,9 @@ impl FileRepo {
                     }
                 })
                 .unwrap();
-            res.push(weight)
+            res.push((m_id.clone(), weight))
         }
-        res.iter().sum()
+        res
     }
 
     fn get_affected_weight_of_target_mod(
@@ -451,6 +856,7 @@ impl CraftRepo for FileRepo {
             .values()
             .find(|i| i.name == search.item_base)
             .unwrap();
+        let item = &self.item_for_query(item, search);
         debug!(
             target: LOG_TARGET,
             "tags for {}: {:?}", search.item_base, item.tags
@@ -475,6 +881,37 @@ impl CraftRepo for FileRepo {
         filter_mods_by_text(&mut res, search.string_query.clone())
     }
 
+    fn find_implicit_mods(&self, search: &ModsQuery) -> Vec<ModItem> {
+        let item = match self.get_item_base_by_item_base(&search.item_base) {
+            Some(item) => self.item_for_query(item, search),
+            None => return vec![],
+        };
+        let mut res = self.create_implicit_items(&item, search.item_level);
+        res.sort_by(|a, b| {
+            a.generation_type
+                .cmp(&b.generation_type)
+                .then_with(|| a.mod_key.to_lowercase().cmp(&b.mod_key.to_lowercase()))
+        });
+        filter_mods_by_text(&mut res, search.string_query.clone())
+    }
+
+    fn string_to_implicit(&self, item_name: &str, mod_name: &str) -> Result<String, String> {
+        let query = ModsQuery {
+            item_base: item_name.to_string(),
+            item_level: 100,
+            string_query: "".to_string(),
+            selected_mods: vec![],
+            influences: vec![],
+            cluster_enchant: None,
+        };
+        let pool: Vec<Candidate> = self
+            .find_implicit_mods(&query)
+            .into_iter()
+            .map(|m| (m, String::new()))
+            .collect();
+        Self::text_to_mod(&pool, mod_name, &ModHint::default())
+    }
+
     fn get_item_classes(&self) -> Vec<String> {
         let mut r: Vec<String> = self.db.item_classes.iter().map(|s| s.clone()).collect();
         r.sort();
@@ -487,7 +924,7 @@ impl CraftRepo for FileRepo {
             .base_items_by_name
             .iter()
             .filter(|(_, bi)| {
-                (bi.domain == "item" || bi.domain == "heist_npc")
+                ITEM_DOMAINS.contains(&bi.domain.as_str())
                     && bi.item_class == item_class.to_string()
             })
             .map(|(s, bi)| ItemBase {
@@ -507,7 +944,7 @@ impl CraftRepo for FileRepo {
             self.db
                 .base_items_by_name
                 .iter()
-                .filter(|(_, bi)| (bi.domain == "item" || bi.domain == "heist_npc"))
+                .filter(|(_, bi)| ITEM_DOMAINS.contains(&bi.domain.as_str()))
                 .map(|(s, bi)| (s.clone(), bi.item_class.clone())),
         )
     }
@@ -526,67 +963,59 @@ impl CraftRepo for FileRepo {
             .ok_or(format!("{} not found in {}", item_name, item_class))
     }
 
-    // parse raw mod string to mod key
-    // provided raw mod string and each available mod for item_base to common template
-    // Idea: bring mod_name to mods representation in db and equal it
     fn string_to_mod(
         &self,
         item_class: &str,
         item_name: &str,
         mod_name: &str,
     ) -> Result<String, String> {
-        let query = ModsQuery {
-            item_base: item_name.to_string(),
-            item_level: 100,
-            string_query: "".to_string(),
-            selected_mods: vec![],
-        };
-        let mods = self.find_mods(&query);
-
-        use regex::Regex;
-
-        //  bring input mod text in representation form
-        //  "blalba +4(2-9) blabla" to "blalba +(2-9) blabla"
+        self.string_to_mod_with_hint(item_class, item_name, mod_name, &ModHint::default())
+    }
 
-        let mod_template = Regex::new(r#"([+-])?(\d+(\.\d+)?)(\([aA-zZ]*)"#)
-            .unwrap()
-            .replace_all(mod_name.trim(), "$1$4");
+    fn string_to_mod_with_hint(
+        &self,
+        _item_class: &str,
+        item_name: &str,
+        mod_name: &str,
+        hint: &ModHint,
+    ) -> Result<String, String> {
+        Self::text_to_mod(&self.named_pool(item_name), mod_name, hint)
+    }
 
-        let multiline_mod = mod_template.contains("\n");
-        let res = mods
-            .into_iter()
-            // .filter(|m| m.representation.contains("increased Evasion and Energy")) // debug, REMOVE!
-            // .filter(|m| m.mod_key == "LocalIncreasedEvasionAndEnergyShieldAndStunRecovery4")
-            .find_map(|m| match multiline_mod {
-                // trivial case, check input mod is equal representation
-                false => match &mod_template == &m.representation {
-                    true => Some(m),
-                    false => None,
-                },
-                // complex case, order of lines may be different for input mod and representation
-                true => {
-                    // cut unequal with cheap operations
-                    match (&m.representation.len() == &mod_template.len())
-                        && m.representation.contains("\n")
-                    {
-                        // compare mods as sorted lines
-                        true => {
-                            let mut v1 = m.representation.split("\n").collect::<Vec<&str>>();
-                            let mut v2 = mod_template.split("\n").collect::<Vec<&str>>();
-                            v1.sort();
-                            v2.sort();
-                            if v1 == v2 {
-                                Some(m)
-                            } else {
-                                None
-                            }
-                        }
-                        false => None,
-                    }
+    fn resolve_mods(
+        &self,
+        _item_class: &str,
+        item_name: &str,
+        mods: &[(String, ModHint)],
+    ) -> Result<Vec<ModResolution>, String> {
+        let pool = self.named_pool(item_name);
+        let index = header_index(&pool);
+        mods.iter()
+            .map(|(mod_name, hint)| {
+                let by_header = header_key(hint).and_then(|key| match index.get(&key) {
+                    Some(mod_ids) if mod_ids.len() == 1 => Some(mod_ids[0].clone()),
+                    _ => None,
+                });
+                let by_text = Self::text_to_mod(&pool, mod_name, hint);
+                match (by_header, by_text) {
+                    (Some(mod_id), Ok(text_mod_id)) if mod_id != text_mod_id => Ok(ModResolution {
+                        warning: Some(format!(
+                            "{} is {} by header, but {} by text",
+                            mod_name, mod_id, text_mod_id
+                        )),
+                        mod_id,
+                    }),
+                    (Some(mod_id), Err(e)) => Ok(ModResolution {
+                        mod_id,
+                        warning: Some(e),
+                    }),
+                    (_, by_text) => by_text.map(|mod_id| ModResolution {
+                        mod_id,
+                        warning: None,
+                    }),
                 }
             })
-            .ok_or(format!("Can't find mod {}", mod_name))?;
-        Ok(res.mod_key)
+            .collect()
     }
 
     fn get_weight_of_target_and_better_mods(
@@ -594,12 +1023,24 @@ impl CraftRepo for FileRepo {
         query: &ModsQuery,
         target_mod_key: String,
     ) -> u32 {
+        self.get_target_and_better_mods(query, &target_mod_key)
+            .iter()
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    fn get_target_and_better_mods(
+        &self,
+        query: &ModsQuery,
+        target_mod_key: &str,
+    ) -> Vec<(String, u32)> {
         let item = self
             .db
             .base_items_by_name
             .values()
             .find(|i| i.name == query.item_base)
             .unwrap();
+        let item = &self.item_for_query(item, query);
         debug!(
             target: LOG_TARGET,
             "tags for {}: {:?}", query.item_base, item.tags
@@ -612,7 +1053,7 @@ impl CraftRepo for FileRepo {
             }
         }
 
-        self.get_weight_of_target_and_better_mods(&mod_ids, item, &target_mod_key, query.item_level)
+        self.find_target_and_better_mods(&mod_ids, item, target_mod_key, query.item_level)
     }
 
     fn get_affected_weight_of_target_mod(&self, query: &ModsQuery) -> u32 {
@@ -622,6 +1063,7 @@ impl CraftRepo for FileRepo {
             .values()
             .find(|i| i.name == query.item_base)
             .unwrap();
+        let item = &self.item_for_query(item, query);
         debug!(
             target: LOG_TARGET,
             "tags for {}: {:?}", query.item_base, item.tags
@@ -664,7 +1106,10 @@ impl CraftRepo for FileRepo {
         let item = self
             .get_item_base_by_item_base(item_base)
             .ok_or("DB inconsistent Error. Can't find item by item_base name")?;
-        let mod_ids_to_check = self.get_mod_ids_for_item(item);
+        let mut mod_ids_to_check =
+            self.get_mod_ids_for_item(&self.influenced_item(item, &spawn_influences(target_mod)));
+        // bench crafts satisfy target too when they are good enough
+        mod_ids_to_check.extend(self.get_bench_mod_ids(&item.item_class));
         // we need to find another mods which meet the stats requeiremetns
         mod_ids_to_check
             .iter()
@@ -676,6 +1121,65 @@ impl CraftRepo for FileRepo {
         Ok(satisfying_mod_ids)
     }
 
+    fn get_cluster_jewel_enchants(&self, item_base: &str) -> Vec<ClusterEnchant> {
+        let mut res: Vec<ClusterEnchant> = self
+            .db
+            .cluster_jewels_by_name
+            .get(item_base)
+            .map(|j| {
+                j.passive_skills
+                    .iter()
+                    .map(|s| ClusterEnchant {
+                        tag: s.tag.clone(),
+                        name: s.name.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        res.sort_by(|a, b| a.name.cmp(&b.name));
+        res
+    }
+
+    fn get_item_category(&self, item_base: &str) -> ItemCategory {
+        self.get_item_base_by_item_base(item_base)
+            .map(|item| ItemCategory::from_domain(&item.domain))
+            .unwrap_or_default()
+    }
+
+    fn get_bench_crafts(&self, item_base: &str) -> Vec<BenchCraft> {
+        let item_class = match self.get_item_base_by_item_base(item_base) {
+            Some(item) => &item.item_class,
+            None => return vec![],
+        };
+        self.db
+            .bench_options
+            .iter()
+            .filter(|o| o.item_classes.contains(item_class))
+            .filter_map(|o| {
+                let mod_key = o.actions.add_explicit_mod.as_ref()?;
+                let m = self.get_mod_by_id(mod_key)?;
+                Some(BenchCraft {
+                    mod_key: mod_key.clone(),
+                    family: m.type_field.clone(),
+                    generation_type: m.generation_type.clone(),
+                    representation: self
+                        .get_mods_representation(m)
+                        .unwrap_or_else(|_| mod_key.clone()),
+                    cost: o
+                        .cost
+                        .iter()
+                        .sorted()
+                        .map(|(currency, count)| CurrencyCost {
+                            // "Metadata/Items/Currency/CurrencyRerollRare" -> "CurrencyRerollRare"
+                            currency: currency.rsplit('/').next().unwrap_or(currency).to_string(),
+                            count: *count,
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
     fn representation_by_mod_id(&self, mod_id: &str) -> String {
         let mod_item = self.get_mod_by_id(mod_id).unwrap();
         self.get_mods_representation(mod_item).unwrap()
@@ -685,6 +1189,7 @@ impl CraftRepo for FileRepo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::files::schemas::SpawnWeight;
     use rstest::{fixture, rstest};
 
     #[fixture]
@@ -692,6 +1197,125 @@ mod tests {
         FileRepo::new().unwrap()
     }
 
+    #[rstest]
+    fn test_header_index() {
+        let mod_item = |mod_key: &str, generation_type: &str, tier: usize| ModItem {
+            generation_type: generation_type.to_string(),
+            tier,
+            ..ModItem::for_test(mod_key)
+        };
+        let pool = vec![
+            (mod_item("IncreasedLife7", "prefix", 1), "Prime".to_string()),
+            (mod_item("IncreasedLife6", "prefix", 2), "Virile".to_string()),
+            (mod_item("FireResist8", "suffix", 1), "of Tzteosh".to_string()),
+            (mod_item("UnnamedMod", "suffix", 1), "".to_string()),
+        ];
+        let index = header_index(&pool);
+        assert_eq!(index.len(), 3);
+        let hint = ModHint {
+            generation_type: Some("prefix".to_string()),
+            name: Some("Virile".to_string()),
+            tier: Some(2),
+        };
+        assert_eq!(
+            index.get(&header_key(&hint).unwrap()),
+            Some(&vec!["IncreasedLife6".to_string()])
+        );
+        assert_eq!(header_key(&ModHint { tier: None, ..hint }), None);
+    }
+
+    #[rstest]
+    fn test_narrow_candidates() {
+        let mod_item = |mod_key: &str, generation_type: &str, tier: usize| ModItem {
+            generation_type: generation_type.to_string(),
+            representation: "+(3-9) to maximum Life".to_string(),
+            family: "IncreasedLife".to_string(),
+            tier,
+            ..ModItem::for_test(mod_key)
+        };
+        let candidates = vec![
+            (mod_item("IncreasedLife1", "prefix", 2), "Hale".to_string()),
+            (mod_item("IncreasedLifeEssence1", "prefix", 1), "of the Essence".to_string()),
+            (mod_item("IncreasedLifeSuffix1", "suffix", 1), "of Life".to_string()),
+        ];
+        let keys = |hint: ModHint| -> Vec<String> {
+            narrow_candidates(candidates.clone(), &hint)
+                .into_iter()
+                .map(|m| m.mod_key)
+                .collect()
+        };
+        assert_eq!(keys(ModHint::default()).len(), 3);
+        let hint = ModHint {
+            generation_type: Some("prefix".to_string()),
+            name: Some("Hale".to_string()),
+            tier: Some(1),
+        };
+        // tier doesn't fit the named mod, so it is ignored
+        assert_eq!(keys(hint), vec!["IncreasedLife1"]);
+        let hint = ModHint {
+            generation_type: Some("prefix".to_string()),
+            name: None,
+            tier: Some(1),
+        };
+        assert_eq!(keys(hint), vec!["IncreasedLifeEssence1"]);
+    }
+
+    #[rstest]
+    fn test_spawn_influences() {
+        let sw = |tag: &str, weight: u32| SpawnWeight {
+            tag: tag.to_string(),
+            weight,
+        };
+        let influenced = Mod {
+            spawn_weights: vec![
+                sw("helmet_basilisk", 1000),
+                sw("amulet_basilisk", 500),
+                sw("helmet_shaper", 0),
+                sw("default", 0),
+            ],
+            ..Mod::default()
+        };
+        assert_eq!(spawn_influences(&influenced), vec![Influence::Hunter]);
+        let plain = Mod {
+            spawn_weights: vec![sw("helmet", 1000), sw("default", 0)],
+            ..Mod::default()
+        };
+        assert!(spawn_influences(&plain).is_empty());
+    }
+
+    #[rstest]
+    fn test_apply_overrides() {
+        let sw = |tag: &str, weight: u32| SpawnWeight {
+            tag: tag.to_string(),
+            weight,
+        };
+        let mut mods = HashMap::from([(
+            "IncreasedLife1".to_string(),
+            Mod {
+                spawn_weights: vec![sw("ring", 1000), sw("default", 0)],
+                ..Mod::default()
+            },
+        )]);
+        let mut representations =
+            HashMap::from([("IncreasedLife1".to_string(), "+(3-9) to Life".to_string())]);
+        let overrides = HashMap::from([
+            (
+                "IncreasedLife1".to_string(),
+                ModOverride {
+                    representation: Some("+(3-9) to maximum Life".to_string()),
+                    weight: Some(500),
+                },
+            ),
+            ("UnknownMod".to_string(), ModOverride::default()),
+        ]);
+        apply_overrides(&mut mods, &mut representations, overrides);
+        assert_eq!(
+            mods["IncreasedLife1"].spawn_weights,
+            vec![sw("ring", 500), sw("default", 0)]
+        );
+        assert_eq!(representations["IncreasedLife1"], "+(3-9) to maximum Life");
+    }
+
     #[rstest]
     #[case("TwoHandChanceToFreeze2".to_string(), "25% chance to Freeze".to_string())]
     #[case("AttackerTakesDamage2".to_string(), "Reflects (5-10) Physical Damage to Melee Attackers".to_string())]
@@ -747,4 +1371,34 @@ mod tests {
         let expected_set: HashSet<String> = HashSet::from_iter(expected);
         assert_eq!(set, expected_set);
     }
+
+    #[rstest]
+    #[case("Large Cluster Jewel", 17)]
+    #[case("Medium Cluster Jewel", 21)]
+    #[case("Gripped Gloves", 0)]
+    fn test_get_cluster_jewel_enchants(
+        repo: FileRepo,
+        #[case] item_base: &str,
+        #[case] expected_count: usize,
+    ) {
+        let enchants = repo.get_cluster_jewel_enchants(item_base);
+        assert_eq!(enchants.len(), expected_count);
+    }
+
+    #[rstest]
+    fn test_get_bench_crafts(repo: FileRepo) {
+        let crafts = repo.get_bench_crafts("Gripped Gloves");
+        let prefix_lock = crafts
+            .iter()
+            .find(|c| c.mod_key == "StrMasterItemGenerationCannotChangePrefixes")
+            .unwrap();
+        assert_eq!(
+            prefix_lock.cost,
+            vec![CurrencyCost {
+                currency: "CurrencyModValues".to_string(),
+                count: 2
+            }]
+        );
+        assert!(repo.get_bench_crafts("Unknown Base").is_empty());
+    }
 }
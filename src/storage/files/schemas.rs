@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -27,6 +28,9 @@ pub struct ItemBaseRich {
     pub domain: String,
     pub release_state: String,
     pub requirements: Option<Requirements>,
+    /// mod ids of base's own implicits
+    #[serde(default)]
+    pub implicits: Vec<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -39,10 +43,62 @@ pub struct Mod {
     pub spawn_weights: Vec<SpawnWeight>,
     pub stats: Vec<Stat>,
     pub groups: Vec<String>,
+    #[serde(default)]
+    pub implicit_tags: Vec<String>,
     #[serde(rename = "type")]
     pub type_field: String,
 }
 
+/// User correction of mod, fields left out keep the data of RePoE
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModOverride {
+    #[serde(default)]
+    pub representation: Option<String>,
+    /// replaces every positive spawn weight, 0 disables the mod
+    #[serde(default)]
+    pub weight: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterJewelSkill {
+    pub id: String,
+    pub name: String,
+    pub stats: HashMap<String, f64>,
+    pub tag: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterJewel {
+    pub name: String,
+    pub size: String,
+    pub min_skills: u32,
+    pub max_skills: u32,
+    pub passive_skills: Vec<ClusterJewelSkill>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterJewelNotable {
+    pub id: String,
+    pub jewel_stat: String,
+    pub name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CraftingBenchActions {
+    #[serde(default)]
+    pub add_explicit_mod: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CraftingBenchOption {
+    pub actions: CraftingBenchActions,
+    pub bench_tier: u32,
+    /// currency metadata id to count
+    pub cost: HashMap<String, u32>,
+    pub item_classes: Vec<String>,
+    pub master: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Hash, Eq)]
 pub struct StatTranslation {
     pub English: Vec<LanguageInstance>,
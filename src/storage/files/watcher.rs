@@ -0,0 +1,99 @@
+use crate::entities::craft_repo::BackEvents;
+use log::info;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const LOG_TARGET: &str = "watcher";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Latest modification time of files in directory, None when it can't be read
+pub fn last_modified(dir: &Path) -> Option<SystemTime> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}
+
+/// Change of data files is reported once they stop changing,
+/// so half-written file doesn't trigger reload
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeDetector {
+    last: Option<SystemTime>,
+    changed: bool,
+}
+
+impl ChangeDetector {
+    pub fn new(last: Option<SystemTime>) -> ChangeDetector {
+        ChangeDetector {
+            last,
+            changed: false,
+        }
+    }
+
+    /// True once per series of changes, on the first poll without new changes
+    pub fn poll(&mut self, current: Option<SystemTime>) -> bool {
+        if current != self.last {
+            self.last = current;
+            self.changed = true;
+            return false;
+        }
+        std::mem::replace(&mut self.changed, false)
+    }
+}
+
+/// Polls modification times of data files and reports settled changes
+pub fn run_watcher_in_background(dir: &str, sender: Sender<BackEvents>) {
+    let dir = Path::new(dir).to_path_buf();
+    thread::spawn(move || {
+        let mut detector = ChangeDetector::new(last_modified(&dir));
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if detector.poll(last_modified(&dir)) {
+                info!(target: LOG_TARGET, "{:?} changed, reloading data", dir);
+                if sender.send(BackEvents::DataChanged).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_single_touch_reports_single_change() {
+        let before = SystemTime::UNIX_EPOCH;
+        let touched = before + Duration::from_secs(1);
+        let mut detector = ChangeDetector::new(Some(before));
+        let reports: Vec<bool> = [before, touched, touched, touched, touched]
+            .into_iter()
+            .map(|t| detector.poll(Some(t)))
+            .collect();
+        assert_eq!(reports, vec![false, false, true, false, false]);
+    }
+
+    #[rstest]
+    fn test_touched_file_is_detected() {
+        let dir = std::env::temp_dir().join(format!("lazy_crafter_watcher_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("mods_overrides.json");
+        fs::write(&file, "{}").unwrap();
+        let mut detector = ChangeDetector::new(last_modified(&dir));
+        let touched = SystemTime::now() + Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(touched)
+            .unwrap();
+        let reports: Vec<bool> = (0..4).map(|_| detector.poll(last_modified(&dir))).collect();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(reports.iter().filter(|r| **r).count(), 1);
+    }
+}
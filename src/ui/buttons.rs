@@ -1,14 +1,75 @@
-use crate::entities::craft_repo::{ModItem, UiEvents};
-use egui::Ui;
+use crate::entities::craft_repo::{BackendStatus, ModItem, UiEvents, UiStates};
+use crate::entities::target::PseudoRule;
+use egui::{Color32, RichText, Ui};
 use std::sync::mpsc;
 
 pub fn show_cleaning_selected_mods_button(
     ui: &mut Ui,
     selected_mods: &mut Vec<ModItem>,
+    excluded_mods: &mut Vec<ModItem>,
     events_sender: &mpsc::Sender<UiEvents>,
 ) {
     if ui.button("clean selected").clicked() {
         selected_mods.clear();
+        excluded_mods.clear();
         events_sender.send(UiEvents::CleanSelectedMods).unwrap();
     }
 }
+
+pub fn show_pseudo_rules_buttons(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut state.pseudo_min_value).clamp_range(0.0..=1000.0));
+        if ui.button("add pseudo").clicked() {
+            let rule = PseudoRule {
+                stat: state.pseudo_stat_choice,
+                min: state.pseudo_min_value,
+            };
+            state.pseudo_rules.push(rule);
+            events_sender.send(UiEvents::ChangeTarget).unwrap();
+        }
+    });
+    let mut removed = None;
+    for (i, rule) in state.pseudo_rules.iter().enumerate() {
+        if ui
+            .button(format!("{} >= {} ✖", rule.stat.label(), rule.min))
+            .clicked()
+        {
+            removed = Some(i);
+        }
+    }
+    if let Some(i) = removed {
+        state.pseudo_rules.remove(i);
+        events_sender.send(UiEvents::ChangeTarget).unwrap();
+    }
+}
+
+/// Data of tables is stale while backend is unavailable, reload creates repo again
+pub fn show_backend_status(
+    ui: &mut Ui,
+    status: &BackendStatus,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    ui.horizontal(|ui| {
+        match status {
+            BackendStatus::Loading => {
+                ui.label("loading data...");
+            }
+            BackendStatus::Ready => (),
+            BackendStatus::Unavailable(err) => {
+                ui.label(RichText::new("backend unavailable").color(Color32::LIGHT_RED))
+                    .on_hover_text(err);
+            }
+        }
+        let text = match status {
+            BackendStatus::Unavailable(_) => "retry",
+            _ => "reload data",
+        };
+        if ui.button(text).clicked() {
+            events_sender.send(UiEvents::ReloadData).unwrap();
+        }
+    });
+}
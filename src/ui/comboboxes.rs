@@ -1,21 +1,22 @@
-use crate::entities::craft_repo::{ItemBase, UiEvents, UiStates};
+use crate::entities::craft_repo::{
+    ClusterEnchant, CraftMode, Influence, ItemBase, ModItem, Reforge, ReforgeKind, UiEvents,
+    UiStates, REFORGE_TAGS,
+};
+use crate::entities::target::{PseudoStat, TargetMode};
 use egui::{Color32, Event, Key, RichText, Ui};
 use std::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use strum::IntoEnumIterator;
 
 pub fn show_combobox_with_bases(
     ui: &mut Ui,
     item_bases: Vec<ItemBase>,
-    ui_states: &Arc<Mutex<UiStates>>,
+    state: &mut UiStates,
     events_sender: &mpsc::Sender<UiEvents>,
     filter_query: &mut String,
 ) {
     let filter = filter_query.clone();
     egui::ComboBox::from_label("item base")
-        .selected_text(format!(
-            "{}",
-            &mut ui_states.lock().unwrap().selected_item_base_as_filter
-        ))
+        .selected_text(format!("{}", &mut state.selected_item_base_as_filter))
         .show_ui(ui, |ui| {
             if &filter.len() > &0 {
                 ui.label(RichText::new(&filter).color(Color32::LIGHT_RED));
@@ -25,12 +26,11 @@ pub fn show_combobox_with_bases(
                 .filter(|v| v.name.to_lowercase().contains(filter.as_str()))
                 .for_each(|i| {
                     let choices = ui.selectable_value(
-                        &mut ui_states.lock().unwrap().selected_item_base_as_filter,
+                        &mut state.selected_item_base_as_filter,
                         i.name.to_string(),
                         format!("{} {}", i.name.to_string(), i.required_level.to_string()),
                     );
                     if choices.changed() {
-                        let state = &mut ui_states.lock().unwrap();
                         state.selected.clear();
                         events_sender.send(UiEvents::AddToSelectedMods).unwrap();
                         filter_query.clear();
@@ -46,16 +46,13 @@ pub fn show_combobox_with_bases(
 pub fn show_combobox_with_classes(
     ui: &mut Ui,
     item_classes: Vec<String>,
-    ui_states: &Arc<Mutex<UiStates>>,
+    state: &mut UiStates,
     events_sender: &mpsc::Sender<UiEvents>,
     filter_query: &mut String,
 ) {
     let filter = filter_query.clone();
     egui::ComboBox::from_label("item class")
-        .selected_text(format!(
-            "{}",
-            &mut ui_states.lock().unwrap().selected_item_class_as_filter
-        ))
+        .selected_text(format!("{}", &mut state.selected_item_class_as_filter))
         .show_ui(ui, |ui| {
             if &filter.len() > &0 {
                 ui.label(RichText::new(&filter).color(Color32::LIGHT_RED));
@@ -65,12 +62,12 @@ pub fn show_combobox_with_classes(
                 .filter(|v| v.to_lowercase().contains(filter.as_str()))
                 .for_each(|i| {
                     let choices = ui.selectable_value(
-                        &mut ui_states.lock().unwrap().selected_item_class_as_filter,
+                        &mut state.selected_item_class_as_filter,
                         i.to_string(),
                         i.to_string(),
                     );
                     if choices.changed() {
-                        let selected_mods = &mut ui_states.lock().unwrap().selected;
+                        let selected_mods = &mut state.selected;
                         selected_mods.clear();
                         filter_query.clear();
                         events_sender.send(UiEvents::AddToSelectedMods).unwrap();
@@ -83,6 +80,191 @@ pub fn show_combobox_with_classes(
         });
 }
 
+pub fn show_combobox_with_target_modes(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    egui::ComboBox::from_label("target")
+        .selected_text(state.target_mode.label())
+        .show_ui(ui, |ui| {
+            for mode in [TargetMode::All, TargetMode::Any, TargetMode::AtLeast] {
+                if ui
+                    .selectable_value(&mut state.target_mode, mode, mode.label())
+                    .changed()
+                {
+                    events_sender.send(UiEvents::ChangeTarget).unwrap();
+                };
+            }
+        });
+}
+
+pub fn show_combobox_with_craft_modes(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    egui::ComboBox::from_label("craft mode")
+        .selected_text(state.craft_mode.label())
+        .show_ui(ui, |ui| {
+            for mode in [CraftMode::Chaos, CraftMode::Alteration] {
+                if ui
+                    .selectable_value(&mut state.craft_mode, mode, mode.label())
+                    .changed()
+                {
+                    events_sender.send(UiEvents::ChangeCraftMode).unwrap();
+                };
+            }
+        });
+}
+
+pub fn show_combobox_with_reforges(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    let selected_tag = state.reforge.as_ref().map(|r| r.tag.clone());
+    let selected_kind = state
+        .reforge
+        .as_ref()
+        .map_or(ReforgeKind::Guaranteed, |r| r.kind);
+    egui::ComboBox::from_label("reforge tag")
+        .selected_text(selected_tag.as_deref().unwrap_or("none"))
+        .show_ui(ui, |ui| {
+            if ui
+                .selectable_label(selected_tag.is_none(), "none")
+                .clicked()
+            {
+                state.reforge = None;
+                events_sender.send(UiEvents::ChangeReforge).unwrap();
+            }
+            for tag in REFORGE_TAGS {
+                if ui
+                    .selectable_label(selected_tag.as_deref() == Some(tag), *tag)
+                    .clicked()
+                {
+                    state.reforge = Some(Reforge {
+                        tag: tag.to_string(),
+                        kind: selected_kind,
+                    });
+                    events_sender.send(UiEvents::ChangeReforge).unwrap();
+                }
+            }
+        });
+    if let Some(reforge) = state.reforge.as_mut() {
+        egui::ComboBox::from_label("reforge kind")
+            .selected_text(reforge.kind.label())
+            .show_ui(ui, |ui| {
+                for kind in [
+                    ReforgeKind::Guaranteed,
+                    ReforgeKind::MoreLikely,
+                    ReforgeKind::LessLikely,
+                ] {
+                    if ui
+                        .selectable_value(&mut reforge.kind, kind, kind.label())
+                        .changed()
+                    {
+                        events_sender.send(UiEvents::ChangeReforge).unwrap();
+                    };
+                }
+            });
+    }
+}
+
+fn drop_mods_of_removed_influences(state: &mut UiStates) {
+    let influences = state.influences.clone();
+    let is_available = |m: &ModItem| m.influence.is_none_or(|i| influences.contains(&i));
+    state.selected.retain(is_available);
+    state.excluded.retain(is_available);
+}
+
+/// Two influence slots for dual influenced bases
+pub fn show_combobox_with_influences(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    for (slot, title) in ["influence", "second influence"].into_iter().enumerate() {
+        if slot > state.influences.len() {
+            break;
+        }
+        let current = state.influences.get(slot).copied();
+        egui::ComboBox::from_label(title)
+            .selected_text(current.map_or("none", |i| i.label()))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(current.is_none(), "none").clicked() {
+                    state.influences.truncate(slot);
+                    drop_mods_of_removed_influences(state);
+                    events_sender.send(UiEvents::ChangeInfluence).unwrap();
+                }
+                for influence in Influence::ALL {
+                    let taken = state.influences.contains(&influence);
+                    if ui
+                        .add_enabled(
+                            !taken || current == Some(influence),
+                            egui::SelectableLabel::new(
+                                current == Some(influence),
+                                influence.label(),
+                            ),
+                        )
+                        .clicked()
+                    {
+                        match slot < state.influences.len() {
+                            true => state.influences[slot] = influence,
+                            false => state.influences.push(influence),
+                        }
+                        drop_mods_of_removed_influences(state);
+                        events_sender.send(UiEvents::ChangeInfluence).unwrap();
+                    }
+                }
+            });
+    }
+}
+
+pub fn show_combobox_with_cluster_enchants(
+    ui: &mut Ui,
+    enchants: Vec<ClusterEnchant>,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    if enchants.is_empty() {
+        return;
+    }
+    let selected_name = enchants
+        .iter()
+        .find(|e| state.cluster_enchant.as_ref() == Some(&e.tag))
+        .map_or("none", |e| e.name.as_str());
+    egui::ComboBox::from_label("enchant")
+        .selected_text(selected_name)
+        .show_ui(ui, |ui| {
+            for enchant in enchants.iter() {
+                if ui
+                    .selectable_label(
+                        state.cluster_enchant.as_ref() == Some(&enchant.tag),
+                        &enchant.name,
+                    )
+                    .clicked()
+                {
+                    state.cluster_enchant = Some(enchant.tag.clone());
+                    // notables of previous enchant can't be rolled anymore
+                    state.selected.clear();
+                    state.excluded.clear();
+                    events_sender.send(UiEvents::ChangeClusterEnchant).unwrap();
+                }
+            }
+        });
+}
+
+pub fn show_combobox_with_pseudo_stats(ui: &mut Ui, state: &mut UiStates) {
+    egui::ComboBox::from_label("pseudo stat")
+        .selected_text(state.pseudo_stat_choice.label())
+        .show_ui(ui, |ui| {
+            for stat in PseudoStat::iter() {
+                ui.selectable_value(&mut state.pseudo_stat_choice, stat, stat.label());
+            }
+        });
+}
+
 fn handle_events(ui: &mut Ui, text: &mut String) {
     ui.input(|i| {
         for event in &i.events {
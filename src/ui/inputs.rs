@@ -1,17 +1,98 @@
-use crate::entities::craft_repo::{UiEvents, UiStates};
+use crate::entities::craft_repo::{MetaCraft, ModsTableFilter, UiEvents, UiStates};
+use crate::entities::target::TargetMode;
 use egui::{Color32, RichText, Ui};
 use log::{debug, error};
-use std::sync::{Arc, Mutex};
 use std::{collections::HashMap, sync::mpsc};
 
 pub fn show_mods_filter_input(
     ui: &mut Ui,
     filter_string: &mut String,
     events_sender: &mpsc::Sender<UiEvents>,
-) {
-    if ui.text_edit_singleline(filter_string).changed() {
+) -> egui::Response {
+    let response = ui.text_edit_singleline(filter_string);
+    if response.changed() {
         events_sender.send(UiEvents::ChangeModFilter).unwrap();
     };
+    response
+}
+
+/// Filter chips of mods table: affix type, tag and minimal required level
+pub fn show_mods_table_filter_chips(
+    ui: &mut Ui,
+    filter: &mut ModsTableFilter,
+    tags: &[String],
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    let mut changed = false;
+    ui.horizontal_wrapped(|ui| {
+        for generation_type in ["prefix", "suffix"] {
+            let active = filter.generation_type.as_deref() == Some(generation_type);
+            if ui.selectable_label(active, generation_type).clicked() {
+                filter.generation_type = match active {
+                    true => None,
+                    false => Some(generation_type.to_string()),
+                };
+                changed = true;
+            }
+        }
+        ui.separator();
+        for tag in tags {
+            let active = filter.tag.as_ref() == Some(tag);
+            if ui.selectable_label(active, tag).clicked() {
+                filter.tag = match active {
+                    true => None,
+                    false => Some(tag.clone()),
+                };
+                changed = true;
+            }
+        }
+        ui.separator();
+        changed |= ui
+            .add(egui::DragValue::new(&mut filter.min_level).clamp_range(0..=100))
+            .changed();
+        ui.label("min lvl");
+    });
+    if changed {
+        events_sender.send(UiEvents::ChangeModsTableView).unwrap();
+    }
+}
+
+pub fn show_target_inputs(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    let mut changed = false;
+    if state.target_mode == TargetMode::AtLeast {
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(egui::DragValue::new(&mut state.target_min_count).clamp_range(1..=6))
+                .changed();
+            ui.label("N");
+        });
+    }
+    ui.horizontal(|ui| {
+        changed |= ui
+            .add(egui::DragValue::new(&mut state.min_open_prefixes).clamp_range(0..=3))
+            .changed();
+        ui.label("open prefixes");
+        changed |= ui
+            .add(egui::DragValue::new(&mut state.min_open_suffixes).clamp_range(0..=3))
+            .changed();
+        ui.label("open suffixes");
+    });
+    ui.label("or custom target (json)");
+    if ui.code_editor(&mut state.custom_target).lost_focus() {
+        changed = true;
+    }
+    if !state.custom_target.trim().is_empty() {
+        if let Err(e) = state.target_expression() {
+            ui.label(RichText::new(e).color(Color32::LIGHT_RED));
+        }
+    }
+    if changed {
+        events_sender.send(UiEvents::ChangeTarget).unwrap();
+    }
 }
 
 /*
@@ -59,25 +140,24 @@ fn parse_item_level_from_string(string: &str) -> Option<u64> {
 pub fn show_item_input(
     ui: &mut Ui,
     classes_by_name: &HashMap<String, String>,
-    ui_states: &Arc<Mutex<UiStates>>,
+    state: &mut UiStates,
     events_sender: &mpsc::Sender<UiEvents>,
 ) {
-    let str = ui_states.lock().unwrap().item_string.clone();
-    let states = &mut ui_states.lock().unwrap();
+    let str = state.item_string.clone();
 
-    if ui.code_editor(&mut states.item_string).lost_focus() {
+    if ui.code_editor(&mut state.item_string).lost_focus() {
         match parse_item_level_from_string(&str) {
             Some(n) => {
-                states.item_level = n.to_string();
-                states.selected_item_level_as_filter = n;
+                state.item_level = n.to_string();
+                state.selected_item_level_as_filter = n;
             }
             None => {}
         }
         for item_name in parse_item_name_from_string(str) {
             match classes_by_name.get(&item_name) {
                 Some(class_name) => {
-                    states.selected_item_class_as_filter = class_name.clone();
-                    states.selected_item_base_as_filter = item_name.to_string();
+                    state.selected_item_class_as_filter = class_name.clone();
+                    state.selected_item_base_as_filter = item_name.to_string();
                     events_sender.send(UiEvents::InsertionItemData).unwrap();
                     return ();
                 }
@@ -88,3 +168,21 @@ pub fn show_item_input(
 }
 
 // show_level_input(ui, item_bases, &self.ui_states, &self.event_tx);
+
+/// Bench meta-crafts used during chaos spam
+pub fn show_meta_craft_checkboxes(
+    ui: &mut Ui,
+    state: &mut UiStates,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    for meta_craft in MetaCraft::ALL {
+        let mut checked = state.meta_crafts.contains(&meta_craft);
+        if ui.checkbox(&mut checked, meta_craft.label()).changed() {
+            match checked {
+                true => state.meta_crafts.push(meta_craft),
+                false => state.meta_crafts.retain(|m| *m != meta_craft),
+            }
+            events_sender.send(UiEvents::ChangeMetaCrafts).unwrap();
+        }
+    }
+}
@@ -1,8 +1,13 @@
-use crate::entities::craft_repo::{ModItem, UiEvents};
+use crate::entities::craft_repo::{
+    BaseRanking, Estimation, IlvlReport, ImplicitKind, ModFamily, ModItem, ModsTableSort, Recipe,
+    UiEvents,
+};
+use crate::usecases::mods_table::pool_share;
 use egui::widget_text::RichText;
-use egui::{Sense, Ui};
+use egui::{Color32, Sense, Ui};
 use egui_extras::{Column, TableBuilder};
 use log::debug;
+use std::collections::{HashMap, HashSet};
 use std::sync::mpsc;
 
 fn calculate_row_height(row: &ModItem, one_row_height: f32) -> f32 {
@@ -13,10 +18,55 @@ fn calculate_row_height(row: &ModItem, one_row_height: f32) -> f32 {
 
 const LOG_TARGET: &str = "ui";
 
+/// Mod text, influence mods are marked and colored
+fn mod_label_text(row: &ModItem) -> RichText {
+    match row.influence {
+        Some(influence) => RichText::new(format!("[{}] {}", influence.label(), row.representation))
+            .color(Color32::from_rgb(180, 130, 255)),
+        None => RichText::new(&row.representation),
+    }
+}
+
+/// Sortable header cell, click on sorted column reverts order
+fn sort_header(
+    ui: &mut Ui,
+    title: &str,
+    column: ModsTableSort,
+    sort: &mut ModsTableSort,
+    descending: &mut bool,
+) -> bool {
+    let arrow = match (*sort == column, *descending) {
+        (true, true) => " ⏷",
+        (true, false) => " ⏶",
+        (false, _) => "",
+    };
+    let clicked = ui
+        .add(
+            egui::Label::new(RichText::new(format!("{}{}", title, arrow)).size(20.0))
+                .sense(Sense::click()),
+        )
+        .clicked();
+    if clicked {
+        match *sort == column {
+            true => *descending = !*descending,
+            false => {
+                *sort = column;
+                *descending = column != ModsTableSort::ModKey;
+            }
+        }
+    }
+    clicked
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn show_table_of_filtered_mods(
     ui: &mut Ui,
     rows: Vec<ModItem>,
+    pool_weight_by_generation_type: &HashMap<String, u32>,
+    sort: &mut ModsTableSort,
+    descending: &mut bool,
     selected: &mut Vec<ModItem>,
+    excluded: &mut Vec<ModItem>,
     events_sender: &mpsc::Sender<UiEvents>,
 ) {
     let table = TableBuilder::new(ui)
@@ -24,16 +74,35 @@ pub fn show_table_of_filtered_mods(
         .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
         .column(Column::initial(30.0).at_least(50.0))
         .column(Column::initial(70.0).at_least(70.0))
+        .column(Column::initial(70.0).at_least(70.0))
+        .column(Column::initial(50.0).at_least(50.0))
+        .column(Column::initial(60.0).at_least(60.0))
+        .column(Column::initial(120.0).at_least(80.0))
         .column(Column::remainder().at_least(300.0))
         .resizable(false);
 
+    let mut sort_changed = false;
     table
         .header(30.0, |mut header| {
             header.col(|ui| {
-                ui.heading("#");
+                sort_changed |= sort_header(ui, "#", ModsTableSort::ModKey, sort, descending);
             });
             header.col(|ui| {
-                ui.heading(RichText::new("weight").size(20.0));
+                sort_changed |= sort_header(ui, "weight", ModsTableSort::Weight, sort, descending);
+            });
+            header.col(|ui| {
+                sort_changed |= sort_header(ui, "%", ModsTableSort::Share, sort, descending);
+            });
+            header.col(|ui| {
+                sort_changed |=
+                    sort_header(ui, "lvl", ModsTableSort::RequiredLevel, sort, descending);
+            });
+            header.col(|ui| {
+                sort_changed |=
+                    sort_header(ui, "type", ModsTableSort::GenerationType, sort, descending);
+            });
+            header.col(|ui| {
+                sort_changed |= sort_header(ui, "tags", ModsTableSort::Tags, sort, descending);
             });
             header.col(|ui| {
                 ui.heading(RichText::new("modification").size(20.0));
@@ -49,22 +118,252 @@ pub fn show_table_of_filtered_mods(
                     row.col(|ui| {
                         ui.label(&rows[row_index].weight.to_string());
                     });
-                    let label = egui::Label::new(&rows[row_index].representation)
+                    row.col(|ui| {
+                        let share = pool_share(&rows[row_index], pool_weight_by_generation_type);
+                        ui.label(format!("{:.2}", share * 100.0));
+                    });
+                    row.col(|ui| {
+                        ui.label(rows[row_index].required_level.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format!(
+                            "{} T{}",
+                            rows[row_index].generation_type, rows[row_index].tier
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.label(rows[row_index].tags.join(", "));
+                    });
+                    let label = egui::Label::new(mod_label_text(&rows[row_index]))
                         .wrap(false)
                         .sense(Sense::click());
                     row.col(|ui| {
-                        if ui.add(label).clicked() {
+                        let response = ui.add(label);
+                        if response.clicked() {
                             selected.push(rows[row_index].clone());
                             events_sender.send(UiEvents::AddToSelectedMods).unwrap();
                             debug!(target: LOG_TARGET, "sent selected event");
+                        } else if response.secondary_clicked() {
+                            excluded.push(rows[row_index].clone());
+                            events_sender.send(UiEvents::AddToSelectedMods).unwrap();
+                            debug!(target: LOG_TARGET, "sent excluded event");
                         };
                     });
                 });
             }
         });
+    if sort_changed {
+        events_sender.send(UiEvents::ChangeModsTableView).unwrap();
+    }
+}
+
+/// Tiers grouped by mod family, click selects the tier or better
+pub fn show_table_of_mod_families(
+    ui: &mut Ui,
+    families: Vec<ModFamily>,
+    selected: &mut Vec<ModItem>,
+    excluded: &mut Vec<ModItem>,
+    events_sender: &mpsc::Sender<UiEvents>,
+) {
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for family in families.iter() {
+            let title = format!(
+                "{} {} ({} tiers, weight {})",
+                family.generation_type,
+                family.family,
+                family.tiers.len(),
+                family.total_weight
+            );
+            egui::CollapsingHeader::new(title)
+                .id_source((&family.generation_type, &family.family))
+                .show(ui, |ui| {
+                    egui::Grid::new((&family.generation_type, &family.family, "tiers"))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("tier");
+                            ui.label("level");
+                            ui.label("weight");
+                            ui.label("share");
+                            ui.label("modification");
+                            ui.end_row();
+                            for t in family.tiers.iter() {
+                                ui.label(format!("T{}", t.tier));
+                                ui.label(t.mod_item.required_level.to_string());
+                                ui.label(t.mod_item.weight.to_string());
+                                ui.label(format!("{:.1}%", t.share * 100.0));
+                                let label = egui::Label::new(mod_label_text(&t.mod_item))
+                                    .wrap(false)
+                                    .sense(Sense::click());
+                                let response = ui.add(label).on_hover_text("this tier or better");
+                                if response.clicked() {
+                                    selected.push(t.mod_item.clone());
+                                    events_sender.send(UiEvents::AddToSelectedMods).unwrap();
+                                    debug!(target: LOG_TARGET, "sent selected event");
+                                } else if response.secondary_clicked() {
+                                    excluded.push(t.mod_item.clone());
+                                    events_sender.send(UiEvents::AddToSelectedMods).unwrap();
+                                    debug!(target: LOG_TARGET, "sent excluded event");
+                                };
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+    });
+}
+
+pub fn show_table_of_base_ranking(ui: &mut Ui, ranking: &[BaseRanking]) {
+    egui::Grid::new("base_ranking")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("base");
+            ui.label("level");
+            ui.label("chance");
+            ui.label("chaos");
+            ui.end_row();
+            for row in ranking.iter() {
+                ui.label(&row.item_base);
+                ui.label(row.required_level.to_string());
+                match row.expected_cost {
+                    Some(cost) => {
+                        ui.label(format!("{:.3}%", row.probability * 100.0));
+                        ui.label(format!("~ {:.0}", cost));
+                    }
+                    None => {
+                        let reason = match &row.error {
+                            Some(error) => error.clone(),
+                            None => format!("can't roll {}", row.missing_families.join(", ")),
+                        };
+                        ui.label("-").on_hover_text(reason);
+                        ui.label("-");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+pub fn show_table_of_implicits(ui: &mut Ui, rows: &[ModItem]) {
+    egui::Grid::new("implicit_mods")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("kind");
+            ui.label("lvl");
+            ui.label("weight");
+            ui.label("mod");
+            ui.end_row();
+            for row in rows.iter() {
+                let kind = ImplicitKind::from_generation_type(&row.generation_type)
+                    .map_or(row.generation_type.as_str(), |k| k.label());
+                ui.label(kind);
+                ui.label(row.required_level.to_string());
+                ui.label(row.weight.to_string());
+                ui.label(mod_label_text(row)).on_hover_text(&row.mod_key);
+                ui.end_row();
+            }
+        });
+}
+
+pub fn show_ilvl_report(ui: &mut Ui, report: &IlvlReport) {
+    egui::Grid::new("ilvl_breakpoints")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("ilvl");
+            ui.label("chance");
+            ui.label("unlocks");
+            ui.end_row();
+            for b in report.breakpoints.iter() {
+                ui.label(b.item_level.to_string());
+                ui.label(format!("{:.3}%", b.probability * 100.0));
+                let unlocked = report
+                    .tiers
+                    .iter()
+                    .filter(|t| b.unlocked.contains(&t.mod_key))
+                    .map(|t| format!("{} T{}", t.family, t.tier))
+                    .collect::<Vec<String>>();
+                ui.label(unlocked.join(", "));
+                ui.end_row();
+            }
+        });
+}
+
+/// Breakdown of chaos estimation, variants are shown only for exact calculation
+pub fn show_estimation(ui: &mut Ui, estimation: &Estimation) {
+    match estimation.expected_tries {
+        Some(tries) => ui.label(format!("~ {:.0} tries", tries)),
+        None => ui.label("target can't be rolled"),
+    };
+    ui.label(format!(
+        "pool weight: prefixes {}, suffixes {}",
+        estimation.prefix_pool_weight, estimation.suffix_pool_weight
+    ));
+    if estimation.approximate {
+        ui.label("affix count ratios of this item are approximate, there is no measured data");
+    }
+    if !estimation.variants.is_empty() {
+        egui::Grid::new("estimation_variants")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("affixes");
+                ui.label("ratio");
+                ui.label("chance");
+                ui.label("contribution");
+                ui.end_row();
+                for v in estimation.variants.iter() {
+                    ui.label(format!("{}p {}s", v.prefix_count, v.suffix_count));
+                    ui.label(format!("{:.2}%", v.ratio * 100.0));
+                    ui.label(format!("{:.3}%", v.probability * 100.0));
+                    ui.label(format!("{:.3}%", v.ratio * v.probability * 100.0));
+                    ui.end_row();
+                }
+            });
+    }
+    for t in estimation.target_weights.iter() {
+        ui.label(format!(
+            "{} or better: {} of {} pool weight {}",
+            t.mod_key, t.weight, t.generation_type, t.pool_weight
+        ))
+        .on_hover_text(format!("counted: {}", t.counted_mods.join(", ")));
+    }
+}
+
+/// Recipes with steps as checklist, checked steps are kept between frames
+pub fn show_recipes(ui: &mut Ui, recipes: &[Recipe], checklist: &mut HashSet<String>) {
+    for recipe in recipes.iter() {
+        let title = match recipe.expected_cost {
+            Some(cost) => format!("{} ~ {:.0} chaos", recipe.name, cost),
+            None => format!("{}, can't be finished", recipe.name),
+        };
+        egui::CollapsingHeader::new(title)
+            .id_source(("recipe", &recipe.name))
+            .show(ui, |ui| {
+                for (i, step) in recipe.steps.iter().enumerate() {
+                    let key = format!("{}/{}", recipe.name, i);
+                    let mut done = checklist.contains(&key);
+                    let label = format!(
+                        "{}. {}: {:.2}%, {:.2} chaos per try",
+                        i + 1,
+                        step.name,
+                        step.probability * 100.0,
+                        step.cost
+                    );
+                    if ui.checkbox(&mut done, label).changed() {
+                        match done {
+                            true => checklist.insert(key),
+                            false => checklist.remove(&key),
+                        };
+                    }
+                }
+            });
+    }
 }
 
-pub fn show_table_of_selected(ui: &mut Ui, rows: Vec<ModItem>) {
+pub fn show_table_of_selected(ui: &mut Ui, rows: Vec<ModItem>, excluded: Vec<ModItem>) {
+    let excluded_rows = excluded.into_iter().map(|m| ModItem {
+        representation: format!("NOT {}", m.representation),
+        ..m
+    });
+    let rows: Vec<ModItem> = rows.into_iter().chain(excluded_rows).collect();
     let selected_table = TableBuilder::new(ui)
         .striped(true)
         .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
@@ -88,7 +387,7 @@ pub fn show_table_of_selected(ui: &mut Ui, rows: Vec<ModItem>) {
                     row.col(|ui| {
                         ui.label((&rows[row_index].weight).to_string());
                     });
-                    let label = egui::Label::new(&rows[row_index].representation)
+                    let label = egui::Label::new(mod_label_text(&rows[row_index]))
                         .wrap(false)
                         .sense(Sense::click());
                     row.col(|ui| {
@@ -1,4 +1,7 @@
-use crate::entities::craft_repo::{BackEvents, Data, Message, UiEvents, UiStates};
+use crate::entities::craft_repo::{
+    AutocraftSettings, BackEvents, BackendStatus, CraftMode, CraftRequest, CraftResponse, Data,
+    Message, UiEvents, UiStates,
+};
 
 use crate::input_schemas::{parse_item_level, parse_max_tries};
 use crate::ui::{buttons, comboboxes, errors, inputs, tables};
@@ -6,58 +9,65 @@ use crate::ui::{buttons, comboboxes, errors, inputs, tables};
 use chrono;
 use eframe::egui;
 use egui::{Visuals, Style};
-use std::sync::{mpsc, Arc, Mutex};
-use std::thread;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, OnceLock};
 
 const APP_NAME: &str = "Lazy Crafter";
 
+/// Context of ui, it is set when window is created.
+/// Background threads request repaint with it to show their results at once
+pub type UiContext = Arc<OnceLock<egui::Context>>;
+
 pub fn run_ui_in_main_thread(
-    sender: mpsc::Sender<UiEvents>,
-    receiver: mpsc::Receiver<BackEvents>,
-    ui_states: Arc<Mutex<UiStates>>,
-    data: Arc<Mutex<Data>>,
+    request_tx: mpsc::Sender<CraftRequest>,
+    response_rx: mpsc::Receiver<CraftResponse>,
+    latest_request_id: Arc<AtomicU64>,
+    back_rx: mpsc::Receiver<BackEvents>,
+    autocraft_tx: mpsc::Sender<AutocraftSettings>,
+    ui_context: UiContext,
 ) {
-    sender.send(UiEvents::Started);
     let mut native_options = eframe::NativeOptions::default();
     native_options.initial_window_size = Some(egui::Vec2 {
         x: 1100.0,
         y: 600.0,
     });
 
-    let ui_states_clone = Arc::clone(&ui_states);
-    thread::spawn(move || {
-        for event in receiver.iter() {
-            match event {
-                BackEvents::Error(err) => {
-                    ui_states_clone.lock().unwrap().messages.push(Message {
-                        text: err.to_string(),
-                        created_at: chrono::Local::now().timestamp(),
-                    });
-                }
-                _ => (),
-            };
-        }
-    });
-
     eframe::run_native(
         APP_NAME,
         native_options,
-        Box::new(|cc| {
+        Box::new(move |cc| {
             let style: Style = Style {
                 visuals: Visuals::dark(),
                 ..Style::default()
             };
             cc.egui_ctx.set_style(style);
-            Box::new(
-            EguiApp::new(cc, ui_states, data, sender))}
-        ),
+            ui_context.set(cc.egui_ctx.clone()).ok();
+            Box::new(EguiApp::new(
+                cc,
+                back_rx,
+                autocraft_tx,
+                request_tx,
+                response_rx,
+                latest_request_id,
+            ))
+        }),
     );
 }
 
+/// Ui states are owned by ui, other threads send messages to it
+/// and get autocraft settings by channel
 struct EguiApp {
-    ui_states: Arc<Mutex<UiStates>>,
-    data: Arc<Mutex<Data>>,
+    ui_states: UiStates,
+    back_rx: mpsc::Receiver<BackEvents>,
+    autocraft_tx: mpsc::Sender<AutocraftSettings>,
+    /// the last settings sent to key listener
+    autocraft_settings: Option<AutocraftSettings>,
+    data: Data,
     event_tx: mpsc::Sender<UiEvents>,
+    event_rx: mpsc::Receiver<UiEvents>,
+    request_tx: mpsc::Sender<CraftRequest>,
+    response_rx: mpsc::Receiver<CraftResponse>,
+    latest_request_id: Arc<AtomicU64>,
     combobox_filter_query: String,
 }
 
@@ -80,53 +90,127 @@ fn setup_custom_fonts(ctx: &egui::Context) {
 impl EguiApp {
     fn new(
         cc: &eframe::CreationContext<'_>,
-        ui_states: Arc<Mutex<UiStates>>,
-        data: Arc<Mutex<Data>>,
-        event_tx: mpsc::Sender<UiEvents>,
+        back_rx: mpsc::Receiver<BackEvents>,
+        autocraft_tx: mpsc::Sender<AutocraftSettings>,
+        request_tx: mpsc::Sender<CraftRequest>,
+        response_rx: mpsc::Receiver<CraftResponse>,
+        latest_request_id: Arc<AtomicU64>,
     ) -> Self {
         setup_custom_fonts(&cc.egui_ctx);
+        // events of widgets are collected during frame and sent as one request
+        let (event_tx, event_rx) = mpsc::channel();
+        event_tx.send(UiEvents::Started).unwrap();
         Self {
-            ui_states,
-            data,
+            ui_states: UiStates::default(),
+            back_rx,
+            autocraft_tx,
+            autocraft_settings: None,
+            data: Data::default(),
             event_tx,
+            event_rx,
+            request_tx,
+            response_rx,
+            latest_request_id,
             combobox_filter_query: String::new(),
         }
     }
+
+    /// Responses of outdated requests are dropped, only the latest one is shown
+    fn receive_responses(&mut self) {
+        let latest_id = self.latest_request_id.load(Ordering::SeqCst);
+        for response in self.response_rx.try_iter() {
+            if response.id == latest_id {
+                self.data = response.data;
+            }
+        }
+    }
+
+    fn receive_back_events(&mut self) {
+        let states = &mut self.ui_states;
+        for event in self.back_rx.try_iter() {
+            match event {
+                BackEvents::Error(err) => {
+                    states.messages.push(Message {
+                        text: err.to_string(),
+                        created_at: chrono::Local::now().timestamp(),
+                    });
+                }
+                BackEvents::BackendLoading => states.backend_status = BackendStatus::Loading,
+                BackEvents::BackendReady => states.backend_status = BackendStatus::Ready,
+                BackEvents::BackendFailed(err) => {
+                    states.backend_status = BackendStatus::Unavailable(err)
+                }
+                // sent to backend with request of this frame
+                BackEvents::DataChanged => {
+                    self.event_tx.send(UiEvents::ReloadData).ok();
+                }
+                BackEvents::CurrencyPositions(positions) => states.currency_positions = positions,
+            };
+        }
+    }
+
+    /// All events of frame become one request with new id
+    fn send_request(&mut self) {
+        let events: Vec<UiEvents> = self.event_rx.try_iter().collect();
+        if events.is_empty() {
+            return;
+        }
+        let id = self.latest_request_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.request_tx
+            .send(self.ui_states.to_request(id, events))
+            .unwrap();
+    }
+
+    /// Key listener gets settings only when they change
+    fn send_autocraft_settings(&mut self) {
+        let settings = self.ui_states.autocraft_settings();
+        if self.autocraft_settings.as_ref() != Some(&settings) {
+            self.autocraft_tx.send(settings.clone()).ok();
+            self.autocraft_settings = Some(settings);
+        }
+    }
 }
 
 impl eframe::App for EguiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.receive_responses();
+        self.receive_back_events();
+        let state = &mut self.ui_states;
         egui::SidePanel::left("input_panel").show(ctx, |ui| {
             ui.heading("Input");
             ui.set_min_width(200.0);
+            buttons::show_backend_status(ui, &state.backend_status, &self.event_tx);
 
-            let item_classes = self.data.lock().unwrap().item_classes.clone();
+            let item_classes = self.data.item_classes.clone();
             comboboxes::show_combobox_with_classes(
                 ui,
                 item_classes,
-                &self.ui_states,
+                state,
                 &self.event_tx,
                 &mut self.combobox_filter_query,
             );
-            let item_bases = self.data.lock().unwrap().item_bases.clone();
+            let item_bases = self.data.item_bases.clone();
             comboboxes::show_combobox_with_bases(
                 ui,
                 item_bases,
-                &self.ui_states,
+                state,
                 &self.event_tx,
                 &mut self.combobox_filter_query,
             );
-            // show_level_input(ui, item_bases, &self.ui_states, &self.event_tx);
+            comboboxes::show_combobox_with_influences(ui, state, &self.event_tx);
+            let cluster_enchants = self.data.cluster_enchants.clone();
+            comboboxes::show_combobox_with_cluster_enchants(
+                ui,
+                cluster_enchants,
+                state,
+                &self.event_tx,
+            );
+            // show_level_input(ui, item_bases, state, &self.event_tx);
             ui.horizontal(|ui| {
                 ui.set_max_width(150.0);
-                if ui
-                    .text_edit_singleline(&mut self.ui_states.lock().unwrap().item_level)
-                    .changed()
-                {
-                    let state = &mut self.ui_states.lock().unwrap();
+                if ui.text_edit_singleline(&mut state.item_level).changed() {
                     match parse_item_level(&state.item_level) {
                         Ok(level) => {
-                            // let state = &mut self.ui_states.lock().unwrap();
                             state.selected_item_level_as_filter = level as u64;
                             self.event_tx.send(UiEvents::ChangeModFilter).unwrap();
                         }
@@ -139,23 +223,66 @@ impl eframe::App for EguiApp {
             ui.label("or paste item");
             inputs::show_item_input(
                 ui,
-                &self.data.lock().unwrap().item_class_by_base_name,
-                &self.ui_states,
+                &self.data.item_class_by_base_name,
+                state,
                 &self.event_tx,
             );
+            match &self.data.item_implicits {
+                Some(Ok(implicits)) => {
+                    for i in implicits.iter() {
+                        let label = ui.label(format!("{}: {}", i.kind.label(), i.text));
+                        if let Some(mod_id) = &i.mod_id {
+                            label.on_hover_text(mod_id);
+                        }
+                    }
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during implicits parsing: {}", err));
+                }
+                None => (),
+            }
+            match &self.data.single_action_estimation {
+                Some(Ok(est)) => {
+                    ui.label(format!(
+                        "open: {} prefixes, {} suffixes",
+                        est.open_prefixes, est.open_suffixes
+                    ));
+                    ui.label(format!(
+                        "exalt adds target ~ {:.2}%, hits ~ {:.2}%",
+                        est.exalt_adds_target * 100.0,
+                        est.exalt_hits_target * 100.0
+                    ));
+                    ui.label(format!(
+                        "annul removes unwanted ~ {:.2}%, keeps target ~ {:.2}%",
+                        est.annul_removes_unwanted * 100.0,
+                        est.annul_keeps_target * 100.0
+                    ));
+                    ui.label(format!(
+                        "aisling hits target ~ {:.2}%",
+                        est.aisling_hits_target * 100.0
+                    ))
+                    .on_hover_text("annuls a mod and adds veiled mod to a side with open slot");
+                    ui.label(format!(
+                        "veiled chaos ~ {:.2}%",
+                        est.veiled_chaos_hits_target * 100.0
+                    ));
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during item estimate: {}", err));
+                }
+                None => (),
+            }
         });
         egui::SidePanel::right("selected_mods_panel").show(ctx, |ui| {
             ui.label("Max autocraft tries:");
             ui.horizontal(|ui| {
                 ui.set_max_width(150.0);
                 if ui
-                    .text_edit_singleline(&mut self.ui_states.lock().unwrap().max_autocraft_tries)
+                    .text_edit_singleline(&mut state.max_autocraft_tries)
                     .changed()
                 {
-                    let state = &mut self.ui_states.lock().unwrap();
                     match parse_max_tries(&state.max_autocraft_tries) {
                         Ok(max) => {
-                            // let state = &mut self.ui_states.lock().unwrap();
                             state.selected_max_autocraft_tries = max as u64;
                         }
                         Err(_) => (),
@@ -164,69 +291,245 @@ impl eframe::App for EguiApp {
             });
 
             ui.heading("Selected");
-            let selected_mods = self.ui_states.lock().unwrap().selected.clone();
-
-            // estimations removed from ui while it not ready
-            // let total_pref_weight: u32 = self
-            //     .data
-            //     .lock()
-            //     .unwrap()
-            //     .mods_table
-            //     .iter()
-            //     .filter(|m| m.generation_type == String::from("prefix"))
-            //     .map(|m| m.weight)
-            //     .sum();
-            // let total_suff_weight: u32 = self
-            //     .data
-            //     .lock()
-            //     .unwrap()
-            //     .mods_table
-            //     .iter()
-            //     .filter(|m| m.generation_type == String::from("suffix"))
-            //     .map(|m| m.weight)
-            //     .sum();
-            // let total_suff_weight: f64 = total_suff_weight.try_into().unwrap();
-            // let total_pref_weight: f64 = total_pref_weight.try_into().unwrap();
-            // let estimation = &self.data.lock().unwrap().estimation;
-            // ui.label(format!("total pref weight: {}", total_pref_weight));
-            // ui.label(format!("total suff weight: {}", total_suff_weight));
-            // match estimation {
-            //     Some(r) => match r {
-            //         Ok(est) => {
-            //             ui.label(format!("estimate ~ {}%", est.probability * 100.0));
-            //         }
-            //         Err(err) => {
-            //             ui.label(format!("Error during estimate: {}", err));
-            //         }
-            //     },
-            //     None => (),
-            // }
-
-            tables::show_table_of_selected(ui, selected_mods);
+            let selected_mods = state.selected.clone();
+
+            match &self.data.estimation {
+                Some(Ok(est)) => {
+                    egui::CollapsingHeader::new(format!(
+                        "estimate ~ {:.3}%{}",
+                        est.probability * 100.0,
+                        if est.approximate { ", approximate" } else { "" }
+                    ))
+                    .id_source("estimation")
+                    .show(ui, |ui| tables::show_estimation(ui, est));
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during estimate: {}", err));
+                }
+                None => (),
+            }
+
+            comboboxes::show_combobox_with_craft_modes(ui, state, &self.event_tx);
+            if state.craft_mode == CraftMode::Alteration {
+                let positions = state.currency_positions.clone();
+                let position_label = |p: Option<(f64, f64)>, hotkey: &str| match p {
+                    Some((x, y)) => format!("({:.0}, {:.0})", x, y),
+                    None => format!("not set, {}", hotkey),
+                };
+                ui.label(format!(
+                    "alteration: {}",
+                    position_label(positions.alteration, "Ctrl+Shift+Q")
+                ));
+                ui.label(format!(
+                    "augmentation: {}",
+                    position_label(positions.augmentation, "Ctrl+Shift+A")
+                ));
+                match &self.data.magic_estimation {
+                    Some(Ok(est)) => {
+                        ui.label(format!(
+                            "alteration ~ {:.2}%, with augmentation ~ {:.2}%",
+                            est.alteration_probability * 100.0,
+                            est.augmentation_probability * 100.0
+                        ));
+                        ui.label(format!(
+                            "~ {:.0} alterations, ~ {:.0} augmentations",
+                            est.expected_alterations, est.expected_augmentations
+                        ));
+                        if let Some(regal) = est.regal_probability {
+                            ui.label(format!("regal ~ {:.2}%", regal * 100.0));
+                        }
+                    }
+                    Some(Err(err)) => {
+                        ui.label(format!("Error during estimate: {}", err));
+                    }
+                    None => (),
+                }
+            }
+
+            ui.collapsing("meta-crafts", |ui| {
+                inputs::show_meta_craft_checkboxes(ui, state, &self.event_tx);
+            });
+            match &self.data.meta_craft_estimation {
+                Some(Ok(est)) => {
+                    ui.label(format!(
+                        "meta-craft chaos ~ {:.2}%, {:.1} chaos per try",
+                        est.probability * 100.0,
+                        est.cost_per_try
+                    ));
+                    if let Some(cost) = est.expected_cost {
+                        ui.label(format!("~ {:.0} chaos total", cost));
+                    }
+                    if let (Some(hits), Some(cost)) = (est.scour_hits_target, est.scour_cost) {
+                        ui.label(format!(
+                            "scour with lock: {}, {:.1} chaos",
+                            if hits { "hits target" } else { "misses target" },
+                            cost
+                        ))
+                        .on_hover_text("scouring orb clears unlocked side, locked mods stay");
+                    }
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during meta-craft estimate: {}", err));
+                }
+                None => (),
+            }
+
+            comboboxes::show_combobox_with_reforges(ui, state, &self.event_tx);
+            match &self.data.reforge_estimation {
+                Some(Ok(est)) => {
+                    ui.label(format!(
+                        "chaos ~ {:.2}%, reforge ~ {:.2}%",
+                        est.chaos_probability * 100.0,
+                        est.reforge_probability * 100.0
+                    ));
+                    for t in est.per_target.iter() {
+                        ui.label(format!(
+                            "{}: chaos ~ {:.2}%, reforge ~ {:.2}%",
+                            t.mod_key,
+                            t.chaos * 100.0,
+                            t.reforge * 100.0
+                        ));
+                    }
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during reforge estimate: {}", err));
+                }
+                None => (),
+            }
+
+            if ui
+                .checkbox(&mut state.compare_bases, "compare bases")
+                .changed()
+            {
+                self.event_tx.send(UiEvents::ChangeBaseComparison).unwrap();
+            }
+            match &self.data.base_ranking {
+                Some(Ok(ranking)) => {
+                    egui::ScrollArea::vertical()
+                        .id_source("base_ranking")
+                        .max_height(200.0)
+                        .show(ui, |ui| tables::show_table_of_base_ranking(ui, ranking));
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during base comparison: {}", err));
+                }
+                None => (),
+            }
+
+            if ui
+                .checkbox(&mut state.ilvl_breakpoints, "ilvl breakpoints")
+                .changed()
+            {
+                self.event_tx.send(UiEvents::ChangeIlvlBreakpoints).unwrap();
+            }
+            match &self.data.ilvl_report {
+                Some(Ok(report)) => {
+                    egui::ScrollArea::vertical()
+                        .id_source("ilvl_breakpoints")
+                        .max_height(200.0)
+                        .show(ui, |ui| tables::show_ilvl_report(ui, report));
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during ilvl analysis: {}", err));
+                }
+                None => (),
+            }
+
+            if ui
+                .checkbox(&mut state.plan_recipes, "plan recipes")
+                .changed()
+            {
+                self.event_tx.send(UiEvents::ChangeRecipePlanner).unwrap();
+            }
+            match &self.data.recipes {
+                Some(Ok(recipes)) => {
+                    let checklist = &mut state.recipe_checklist;
+                    tables::show_recipes(ui, recipes, checklist);
+                }
+                Some(Err(err)) => {
+                    ui.label(format!("Error during recipe planning: {}", err));
+                }
+                None => (),
+            }
+
+            let excluded_mods = state.excluded.clone();
+            comboboxes::show_combobox_with_target_modes(ui, state, &self.event_tx);
+            inputs::show_target_inputs(ui, state, &self.event_tx);
+            comboboxes::show_combobox_with_pseudo_stats(ui, state);
+            buttons::show_pseudo_rules_buttons(ui, state, &self.event_tx);
 
+            tables::show_table_of_selected(ui, selected_mods, excluded_mods);
             buttons::show_cleaning_selected_mods_button(
                 ui,
-                &mut self.ui_states.lock().unwrap().selected,
+                &mut state.selected,
+                &mut state.excluded,
                 &self.event_tx,
             );
 
             ui.with_layout(egui::Layout::top_down(egui::Align::Min), |ui| {
-                errors::show_errors(ui, &mut self.ui_states.lock().unwrap().messages);
+                errors::show_errors(ui, &mut state.messages);
             });
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Mods");
             ui.horizontal(|ui| {
-                let filter_string = &mut self.ui_states.lock().unwrap().filter_string;
+                let filter_string = &mut state.filter_string;
                 ui.label("filter: ");
-                inputs::show_mods_filter_input(ui, filter_string, &self.event_tx);
+                inputs::show_mods_filter_input(ui, filter_string, &self.event_tx).on_hover_text(
+                    "fuzzy text, tag:life, id:IncreasedLife, stat:base_maximum_life, re:<regex>",
+                );
+                ui.checkbox(&mut state.grouped_mods_view, "group tiers");
+                if ui
+                    .checkbox(&mut state.implicits_table, "implicits")
+                    .changed()
+                {
+                    self.event_tx.send(UiEvents::ChangeImplicitsTable).unwrap();
+                }
             });
 
-            let mod_items = self.data.lock().unwrap().mods_table.clone();
-            let selected_mods = &mut self.ui_states.lock().unwrap().selected;
-            tables::show_table_of_filtered_mods(ui, mod_items, selected_mods, &self.event_tx);
+            if let Some(e) = &self.data.search_error {
+                ui.label(egui::RichText::new(e).color(egui::Color32::LIGHT_RED));
+            }
+            let mod_tags = self.data.mod_tags.clone();
+            inputs::show_mods_table_filter_chips(
+                ui,
+                &mut state.mods_table_filter,
+                &mod_tags,
+                &self.event_tx,
+            );
+            if state.implicits_table {
+                let implicit_mods = self.data.implicit_mods.clone().unwrap_or_default();
+                egui::ScrollArea::vertical()
+                    .id_source("implicit_mods")
+                    .show(ui, |ui| tables::show_table_of_implicits(ui, &implicit_mods));
+            } else if state.grouped_mods_view {
+                let mod_families = self.data.mod_families.clone();
+                tables::show_table_of_mod_families(
+                    ui,
+                    mod_families,
+                    &mut state.selected,
+                    &mut state.excluded,
+                    &self.event_tx,
+                );
+            } else {
+                let mod_items = self.data.mods_table.clone();
+                let pool_weight_by_generation_type =
+                    self.data.pool_weight_by_generation_type.clone();
+                tables::show_table_of_filtered_mods(
+                    ui,
+                    mod_items,
+                    &pool_weight_by_generation_type,
+                    &mut state.mods_table_sort,
+                    &mut state.mods_table_sort_descending,
+                    &mut state.selected,
+                    &mut state.excluded,
+                    &self.event_tx,
+                );
+            }
         });
+        self.send_request();
+        self.send_autocraft_settings();
         ctx.request_repaint_after(std::time::Duration::from_secs(1));
         ()
     }
@@ -1,4 +1,16 @@
+pub mod base_comparison;
 pub mod craft_searcher;
+pub mod currency;
 pub mod estimation;
+pub mod ilvl_breakpoints;
 pub mod item_parser;
 pub mod matcher;
+pub mod meta_crafting;
+pub mod mod_families;
+pub mod mod_search;
+pub mod mods_table;
+pub mod pseudo_stats;
+pub mod rules;
+pub mod simulation;
+pub mod single_action;
+pub mod strategy_planner;
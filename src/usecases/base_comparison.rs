@@ -0,0 +1,174 @@
+use crate::entities::craft_repo::{BaseRanking, CraftRepo, ModItem, ModsQuery};
+use crate::usecases::estimation::calculate_estimation_for_craft;
+
+/// Lowest tier of target family on other base which is at least as good as target.
+/// Families without such tier on the base are returned as error
+pub fn resolve_target_mods(
+    targets: &[ModItem],
+    pool: &[ModItem],
+) -> Result<Vec<ModItem>, Vec<String>> {
+    let mut resolved = vec![];
+    let mut missing = vec![];
+    for target in targets.iter() {
+        let same_mod = pool.iter().find(|m| m.mod_key == target.mod_key);
+        let same_family = || {
+            pool.iter()
+                .filter(|m| {
+                    m.family == target.family
+                        && m.generation_type == target.generation_type
+                        && m.required_level >= target.required_level
+                })
+                .min_by_key(|m| m.required_level)
+        };
+        match same_mod.or_else(same_family) {
+            Some(m) => resolved.push(m.clone()),
+            None => missing.push(target.family.clone()),
+        }
+    }
+    match missing.is_empty() {
+        true => Ok(resolved),
+        false => Err(missing),
+    }
+}
+
+/// Bases with higher chance go first, bases without target mods are the last ones
+pub fn sort_ranking(ranking: &mut [BaseRanking]) {
+    ranking.sort_by(|a, b| {
+        b.probability
+            .total_cmp(&a.probability)
+            .then_with(|| a.item_base.cmp(&b.item_base))
+    });
+}
+
+/// Chaos estimation of selected mods for every base of item class
+pub fn rank_bases(
+    repo: &impl CraftRepo,
+    item_class: &str,
+    query: &ModsQuery,
+) -> Result<Vec<BaseRanking>, String> {
+    if query.selected_mods.is_empty() {
+        return Err("no mods selected".to_string());
+    }
+    let mut ranking = vec![];
+    for base in repo.get_item_bases(item_class).into_iter() {
+        // base can't drop at lower item level
+        if base.required_level > query.item_level {
+            continue;
+        }
+        let pool = repo.find_mods(&ModsQuery {
+            string_query: "".to_string(),
+            item_base: base.name.clone(),
+            selected_mods: vec![],
+            ..query.clone()
+        });
+        let (probability, missing_families, error) =
+            match resolve_target_mods(&query.selected_mods, &pool) {
+                Ok(selected_mods) => {
+                    let base_query = ModsQuery {
+                        string_query: "".to_string(),
+                        item_base: base.name.clone(),
+                        selected_mods,
+                        ..query.clone()
+                    };
+                    // one broken base doesn't hide the others
+                    match calculate_estimation_for_craft(repo, &base_query) {
+                        Ok(estimation) => (estimation.probability, vec![], None),
+                        Err(e) => (0.0, vec![], Some(e)),
+                    }
+                }
+                Err(missing) => (0.0, missing, None),
+            };
+        ranking.push(BaseRanking {
+            item_base: base.name,
+            required_level: base.required_level,
+            probability,
+            expected_cost: (probability > 0.0).then(|| 1.0 / probability),
+            missing_families,
+            error,
+        });
+    }
+    sort_ranking(&mut ranking);
+    Ok(ranking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn mod_item(mod_key: &str, family: &str, required_level: u64) -> ModItem {
+        ModItem {
+            required_level,
+            family: family.to_string(),
+            groups: vec![family.to_string()],
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn keys(mods: Vec<ModItem>) -> Vec<String> {
+        mods.into_iter().map(|m| m.mod_key).collect()
+    }
+
+    #[rstest]
+    fn test_resolve_target_mods() {
+        let pool = vec![
+            mod_item("EnergyShield5", "DefencesPercent", 60),
+            mod_item("EnergyShield4", "DefencesPercent", 46),
+            mod_item("IncreasedLife4", "IncreasedLife", 30),
+        ];
+        // armour tier of other base becomes energy shield tier which is at least as good
+        let targets = vec![
+            mod_item("Armour5", "DefencesPercent", 50),
+            mod_item("IncreasedLife4", "IncreasedLife", 30),
+        ];
+        assert_eq!(
+            keys(resolve_target_mods(&targets, &pool).unwrap()),
+            vec!["EnergyShield5", "IncreasedLife4"]
+        );
+        let targets = vec![mod_item("Armour4", "DefencesPercent", 40)];
+        assert_eq!(
+            keys(resolve_target_mods(&targets, &pool).unwrap()),
+            vec!["EnergyShield4"]
+        );
+        // base has no tier good enough
+        let targets = vec![mod_item("Armour6", "DefencesPercent", 70)];
+        assert_eq!(
+            resolve_target_mods(&targets, &pool).unwrap_err(),
+            vec!["DefencesPercent"]
+        );
+        let targets = vec![mod_item("MovementVelocity3", "MovementVelocity", 30)];
+        assert_eq!(
+            resolve_target_mods(&targets, &pool).unwrap_err(),
+            vec!["MovementVelocity"]
+        );
+    }
+
+    #[rstest]
+    fn test_sort_ranking() {
+        let ranking = |item_base: &str, probability: f64| BaseRanking {
+            item_base: item_base.to_string(),
+            required_level: 1,
+            probability,
+            expected_cost: None,
+            missing_families: vec![],
+            error: None,
+        };
+        let mut rows = vec![
+            ranking("Wool Shoes", 0.0),
+            ranking("Iron Greaves", 0.01),
+            ranking("Leatherscale Boots", 0.02),
+            ranking("Goathide Boots", 0.01),
+        ];
+        sort_ranking(&mut rows);
+        let names: Vec<&str> = rows.iter().map(|r| r.item_base.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Leatherscale Boots",
+                "Goathide Boots",
+                "Iron Greaves",
+                "Wool Shoes"
+            ]
+        );
+    }
+}
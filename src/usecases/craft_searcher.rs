@@ -1,11 +1,28 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::entities::craft_repo::{CraftRepo, ItemBase, ModItem, ModsQuery};
+use crate::entities::craft_repo::{
+    BenchCraft, ClusterEnchant, CraftRepo, ItemBase, ModItem, ModsQuery,
+};
+use crate::usecases::mod_search;
 
 pub fn find_mods(repo: &impl CraftRepo, query: &ModsQuery) -> Vec<ModItem> {
     repo.find_mods(query)
 }
 
+/// Implicits and enchants which can be on base of query
+pub fn find_implicit_mods(repo: &impl CraftRepo, query: &ModsQuery) -> Vec<ModItem> {
+    repo.find_implicit_mods(query)
+}
+
+/// Mods of query ranked by search engine, see `mod_search` for query syntax
+pub fn search_mods(repo: &impl CraftRepo, query: &ModsQuery) -> Result<Vec<ModItem>, String> {
+    let mods = repo.find_mods(&ModsQuery {
+        string_query: "".to_string(),
+        ..query.clone()
+    });
+    mod_search::search_mods(mods, &query.string_query)
+}
+
 pub fn get_item_classes(repo: &impl CraftRepo) -> Vec<String> {
     repo.get_item_classes()
 }
@@ -14,6 +31,14 @@ pub fn get_item_bases(repo: &impl CraftRepo, item_class: &str) -> Vec<ItemBase>
     repo.get_item_bases(item_class)
 }
 
+pub fn get_cluster_jewel_enchants(repo: &impl CraftRepo, item_base: &str) -> Vec<ClusterEnchant> {
+    repo.get_cluster_jewel_enchants(item_base)
+}
+
+pub fn get_bench_crafts(repo: &impl CraftRepo, item_base: &str) -> Vec<BenchCraft> {
+    repo.get_bench_crafts(item_base)
+}
+
 pub fn get_item_class_by_item_name(repo: &impl CraftRepo) -> HashMap<String, String> {
     repo.get_item_class_by_item_name()
 }
@@ -26,10 +51,22 @@ pub fn get_weight_of_target_and_better_mods(
     repo.get_weight_of_target_and_better_mods(query, target_mod_key)
 }
 
+pub fn get_target_and_better_mods(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    target_mod_key: &str,
+) -> Vec<(String, u32)> {
+    repo.get_target_and_better_mods(query, target_mod_key)
+}
+
 pub fn get_affected_weight_of_target_mod(repo: &impl CraftRepo, query: &ModsQuery) -> u32 {
     repo.get_affected_weight_of_target_mod(query)
 }
 
-pub fn get_set_of_better_mods(repo: &impl CraftRepo, mod_id: &str, item_base: &str) -> Result<HashSet<String>, String> {
+pub fn get_set_of_better_mods(
+    repo: &impl CraftRepo,
+    mod_id: &str,
+    item_base: &str,
+) -> Result<HashSet<String>, String> {
     repo.get_subset_of_mods(mod_id, item_base)
 }
@@ -0,0 +1,82 @@
+use crate::entities::craft_repo::CurrencyCost;
+
+pub const CHAOS: &str = "CurrencyRerollRare";
+pub const ALTERATION: &str = "CurrencyRerollMagic";
+pub const AUGMENTATION: &str = "CurrencyAddModToMagic";
+pub const REGAL: &str = "CurrencyUpgradeMagicToRare";
+pub const EXALT: &str = "CurrencyAddModToRare";
+pub const ANNUL: &str = "CurrencyRemoveMod";
+pub const SCOUR: &str = "CurrencyConvertToNormal";
+pub const DIVINE: &str = "CurrencyModValues";
+
+// (currency id, label, price in chaos orbs), prices are rough defaults
+const CURRENCIES: &[(&str, &str, f64)] = &[
+    (CHAOS, "Chaos Orb", 1.0),
+    (ALTERATION, "Orb of Alteration", 0.1),
+    (AUGMENTATION, "Orb of Augmentation", 0.05),
+    (REGAL, "Regal Orb", 0.3),
+    (EXALT, "Exalted Orb", 15.0),
+    (ANNUL, "Orb of Annulment", 3.0),
+    (SCOUR, "Orb of Scouring", 0.5),
+    (DIVINE, "Divine Orb", 150.0),
+    ("CurrencyUpgradeToMagic", "Orb of Transmutation", 0.05),
+    ("CurrencyUpgradeToRare", "Orb of Alchemy", 0.2),
+    ("CurrencyUpgradeRandomly", "Orb of Chance", 0.1),
+    ("CurrencyCorrupt", "Vaal Orb", 1.0),
+    ("CurrencyFlaskQuality", "Glassblower's Bauble", 0.2),
+    ("CurrencyArmourQuality", "Armourer's Scrap", 0.05),
+    ("CurrencyGemQuality", "Gemcutter's Prism", 1.0),
+    ("CurrencyRerollSocketColours", "Chromatic Orb", 0.1),
+    ("CurrencyRerollSocketNumbers", "Jeweller's Orb", 0.1),
+    ("CurrencyRerollSocketLinks", "Orb of Fusing", 0.2),
+    ("CurrencyInstillingOrb", "Instilling Orb", 1.0),
+];
+
+pub fn currency_label(currency: &str) -> &str {
+    CURRENCIES
+        .iter()
+        .find(|(id, _, _)| *id == currency)
+        .map(|(_, label, _)| *label)
+        .unwrap_or(currency)
+}
+
+/// Price in chaos orbs, unknown currency costs nothing
+pub fn currency_price(currency: &str) -> f64 {
+    CURRENCIES
+        .iter()
+        .find(|(id, _, _)| *id == currency)
+        .map(|(_, _, price)| *price)
+        .unwrap_or(0.0)
+}
+
+pub fn cost_in_chaos(cost: &[CurrencyCost]) -> f64 {
+    cost.iter()
+        .map(|c| currency_price(&c.currency) * c.count as f64)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_cost_in_chaos() {
+        let cost = vec![
+            CurrencyCost {
+                currency: CHAOS.to_string(),
+                count: 4,
+            },
+            CurrencyCost {
+                currency: DIVINE.to_string(),
+                count: 2,
+            },
+            CurrencyCost {
+                currency: "CurrencyUnknown".to_string(),
+                count: 1,
+            },
+        ];
+        assert_eq!(cost_in_chaos(&cost), 304.0);
+        assert_eq!(currency_label(REGAL), "Regal Orb");
+    }
+}
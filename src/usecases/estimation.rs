@@ -1,93 +1,100 @@
 use log::debug;
 
-use crate::entities::craft_repo::{CraftRepo, Estimation, ItemBase, ModItem, ModsQuery};
+use crate::entities::craft_repo::{
+    CraftRepo, Estimation, ItemBase, ItemCategory, MagicEstimation, ModItem, ModsQuery, Reforge,
+    ReforgeEstimation, TargetProbability, TargetWeight, VariantContribution,
+};
+use crate::entities::target::TargetExpr;
 use crate::usecases::craft_searcher::{
-    get_affected_weight_of_target_mod, get_weight_of_target_and_better_mods,
+    get_affected_weight_of_target_mod, get_target_and_better_mods,
+    get_weight_of_target_and_better_mods,
+};
+use crate::usecases::matcher::{check_rollable_target, ModMatcher};
+use crate::usecases::rules::{
+    affix_count_ratio, affix_count_variants, chaos_ratios_are_approximate, rare_affix_limits,
+    Rarity,
+};
+use crate::usecases::simulation::{
+    build_pool, simulate_chaos_for_matcher, simulate_rolls, DEFAULT_SIMULATION_TRIES,
 };
 use itertools::Itertools;
 
-fn chaos_variants_ratio(prefix_count: usize, suffix_count: usize) -> f64 {
-    match (prefix_count, suffix_count) {
-        (1, 3) => 0.2814,
-        (2, 2) => 0.2836,
-        (2, 3) => 0.1725,
-        (3, 1) => 0.1016,
-        (3, 2) => 0.0775,
-        (3, 3) => 0.0833,
-        _ => 0.0,
-    }
-}
-
-fn probability_for_variant(
-    repo: &impl CraftRepo,
-    prefix_count: usize,
-    suffix_count: usize,
-    item_base: String,
-    item_level: u64,
-    selected_mods: &Vec<(ModItem, u32)>,
-    available_mods: &Vec<ModItem>,
+/// Chance of target mods of one affix side to be among `affix_count` rolled mods of the side.
+/// Every order of target mods and other mods is summed, rolled mod removes its affected weight
+fn side_probability(
+    generation_type: &str,
+    affix_count: usize,
+    selected_mods: &[(ModItem, u32)],
+    available_mods: &[ModItem],
+    affected_weight: &impl Fn(&ModItem) -> u32,
 ) -> f64 {
-    let selected_prefixes = selected_mods
+    let side_targets = selected_mods
         .iter()
-        .filter(|(m, _w)| m.generation_type == "prefix")
-        .map(|(m, _w)| m)
-        .collect::<Vec<&ModItem>>();
+        .filter(|(m, _w)| m.generation_type == generation_type)
+        .collect::<Vec<&(ModItem, u32)>>();
 
-    let target_prefixes = selected_prefixes
+    let mut mod_keys = side_targets
         .iter()
-        .map(|m| m.mod_key.clone())
+        .map(|(m, _w)| m.mod_key.clone())
         .collect::<Vec<String>>();
-    let mut prefixes_mod_keys = target_prefixes.clone();
-    for _i in 1..(prefix_count - selected_prefixes.len() + 1) {
-        prefixes_mod_keys.push(String::from("FAKE"));
+    for _i in 1..(affix_count - side_targets.len() + 1) {
+        mod_keys.push(String::from("FAKE"));
     }
-    debug!("prefixes_mod_keys: {:?}", prefixes_mod_keys);
+    debug!("{} mod_keys: {:?}", generation_type, mod_keys);
     let weight = available_mods
         .iter()
-        .filter(|m| m.generation_type == "prefix")
+        .filter(|m| m.generation_type == generation_type)
         .map(|m| m.weight)
         .sum::<u32>();
 
     let mut cases_probability: Vec<f64> = vec![0.0];
 
-    for permutation in prefixes_mod_keys.iter().permutations(prefix_count).unique() {
+    for permutation in mod_keys.iter().permutations(affix_count).unique() {
         let mut local_weight = weight;
         let mut local_p = vec![1.0];
         for mod_key in permutation.iter() {
-            let w;
-            let affected_weight;
-            if *mod_key == "FAKE" {
-                w = (local_weight as f64 * 0.1) as u32;
-                affected_weight = w;
+            let removed_weight = if *mod_key == "FAKE" {
+                (local_weight as f64 * 0.1) as u32
             } else {
-                w = selected_mods
+                let (target, w) = side_targets
                     .iter()
                     .find(|(m, _w)| m.mod_key == **mod_key)
-                    .unwrap()
-                    .1;
-                local_p.push(w as f64 / local_weight as f64);
-                let q = ModsQuery {
-                    string_query: "".to_string(),
-                    item_base: item_base.clone(),
-                    item_level,
-                    selected_mods: vec![selected_mods
-                        .iter()
-                        .find(|(m, _w)| m.mod_key == **mod_key)
-                        .unwrap()
-                        .0
-                        .clone()],
-                };
-                affected_weight = get_affected_weight_of_target_mod(repo, &q);
-            }
-            local_weight -= affected_weight; // affected weight is not same as target weight
+                    .unwrap();
+                local_p.push(*w as f64 / local_weight as f64);
+                // affected weight is not same as target weight
+                affected_weight(target)
+            };
+            local_weight = local_weight.saturating_sub(removed_weight);
         }
         cases_probability.push(local_p.iter().product());
         debug!("permutation: {:?}", permutation);
     }
-    let res_sum = cases_probability.iter().sum();
+    cases_probability.iter().sum()
+}
 
-    debug!("p{}s{}: prob={}", prefix_count, suffix_count, res_sum);
-    res_sum
+/// Prefixes and suffixes are rolled independently, so chances of both sides multiply
+fn probability_for_variant(
+    prefix_count: usize,
+    suffix_count: usize,
+    selected_mods: &[(ModItem, u32)],
+    available_mods: &[ModItem],
+    affected_weight: &impl Fn(&ModItem) -> u32,
+) -> f64 {
+    let res = side_probability(
+        "prefix",
+        prefix_count,
+        selected_mods,
+        available_mods,
+        affected_weight,
+    ) * side_probability(
+        "suffix",
+        suffix_count,
+        selected_mods,
+        available_mods,
+        affected_weight,
+    );
+    debug!("p{}s{}: prob={}", prefix_count, suffix_count, res);
+    res
 }
 
 pub fn calculate_estimation_for_craft(
@@ -108,7 +115,9 @@ pub fn calculate_estimation_for_craft(
         .filter(|m| m.generation_type == "suffix")
         .count();
 
-    if required_prefix_count > 3 || required_suffix_count > 3 {
+    let category = repo.get_item_category(&query.item_base);
+    let limits = rare_affix_limits(category)?;
+    if required_prefix_count > limits.prefixes || required_suffix_count > limits.suffixes {
         return Err("too many affixes selected".to_string());
     }
     debug!(
@@ -117,9 +126,9 @@ pub fn calculate_estimation_for_craft(
     );
 
     let mut variant_with_ratios = vec![];
-    for pc in required_prefix_count..3 + 1 {
-        for sc in required_suffix_count..3 + 1 {
-            let ratio = chaos_variants_ratio(pc, sc);
+    for pc in required_prefix_count..limits.prefixes + 1 {
+        for sc in required_suffix_count..limits.suffixes + 1 {
+            let ratio = affix_count_ratio(category, Rarity::Rare, pc, sc);
             if ratio == 0.0 {
                 continue;
             }
@@ -133,34 +142,422 @@ pub fn calculate_estimation_for_craft(
         item_base: query.item_base.clone(),
         item_level: query.item_level,
         selected_mods: vec![],
+        influences: query.influences.clone(),
+        cluster_enchant: query.cluster_enchant.clone(),
     };
     let available_mods = repo.find_mods(&available_mods_query);
 
-    let target_mods_with_weights = selected_mods
+    let counted_mods_by_target: Vec<(ModItem, Vec<(String, u32)>)> = selected_mods
         .iter()
         .map(|m| {
             (
                 m.clone(),
-                get_weight_of_target_and_better_mods(repo, query, m.mod_key.clone()),
+                get_target_and_better_mods(repo, query, &m.mod_key),
             )
         })
         .collect();
+    let target_mods_with_weights: Vec<(ModItem, u32)> = counted_mods_by_target
+        .iter()
+        .map(|(m, counted)| (m.clone(), counted.iter().map(|(_, w)| w).sum()))
+        .collect();
 
     debug!("target_mods_with_weights: {:?}", target_mods_with_weights);
+    let affected_weight = |target: &ModItem| {
+        let q = ModsQuery {
+            string_query: "".to_string(),
+            item_base: query.item_base.clone(),
+            item_level: query.item_level,
+            selected_mods: vec![target.clone()],
+            influences: query.influences.clone(),
+            cluster_enchant: query.cluster_enchant.clone(),
+        };
+        get_affected_weight_of_target_mod(repo, &q)
+    };
 
-    let sum: f64 = variant_with_ratios
+    let variants: Vec<VariantContribution> = variant_with_ratios
         .iter()
-        .map(|(pc, sc, ratio)| {
-            probability_for_variant(
-                repo,
+        .map(|(pc, sc, ratio)| VariantContribution {
+            prefix_count: *pc,
+            suffix_count: *sc,
+            ratio: *ratio,
+            probability: probability_for_variant(
                 *pc,
                 *sc,
-                query.item_base.clone(),
-                query.item_level,
                 &target_mods_with_weights,
                 &available_mods,
-            ) * ratio
+                &affected_weight,
+            ),
+        })
+        .collect();
+    let sum: f64 = variants.iter().map(|v| v.probability * v.ratio).sum();
+    let target_weights = counted_mods_by_target
+        .iter()
+        .map(|(m, counted)| explain_target_weight(m, counted, &available_mods))
+        .collect();
+    Ok(estimation(
+        sum,
+        variants,
+        &available_mods,
+        target_weights,
+        category,
+    ))
+}
+
+fn pool_weight(pool: &[ModItem], gen_type: &str) -> u32 {
+    pool.iter()
+        .filter(|m| m.generation_type == gen_type)
+        .map(|m| m.weight)
+        .sum()
+}
+
+/// Weight of target is the sum of mods counted by repo, better tiers go first
+pub fn explain_target_weight(
+    target: &ModItem,
+    counted: &[(String, u32)],
+    pool: &[ModItem],
+) -> TargetWeight {
+    let tier_of = |mod_key: &String| {
+        pool.iter()
+            .find(|m| &m.mod_key == mod_key)
+            .map_or(usize::MAX, |m| m.tier)
+    };
+    TargetWeight {
+        mod_key: target.mod_key.clone(),
+        generation_type: target.generation_type.clone(),
+        weight: counted.iter().map(|(_, weight)| weight).sum(),
+        pool_weight: pool_weight(pool, &target.generation_type),
+        counted_mods: counted
+            .iter()
+            .map(|(mod_key, _)| mod_key.clone())
+            .sorted_by_key(tier_of)
+            .collect(),
+    }
+}
+
+fn estimation(
+    probability: f64,
+    variants: Vec<VariantContribution>,
+    pool: &[ModItem],
+    target_weights: Vec<TargetWeight>,
+    category: ItemCategory,
+) -> Estimation {
+    Estimation {
+        probability,
+        expected_tries: (probability > 0.0).then(|| 1.0 / probability),
+        variants,
+        prefix_pool_weight: pool_weight(pool, "prefix"),
+        suffix_pool_weight: pool_weight(pool, "suffix"),
+        target_weights,
+        approximate: chaos_ratios_are_approximate(category),
+    }
+}
+
+/// Estimation for boolean target.
+/// Plain AND of mods is calculated exactly, other expressions are simulated.
+pub fn calculate_estimation_for_target(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    target: &TargetExpr,
+    is_stale: &dyn Fn() -> bool,
+) -> Result<Estimation, String> {
+    check_rollable_target(target)?;
+    let available_mods_query = ModsQuery {
+        string_query: "".to_string(),
+        item_base: query.item_base.clone(),
+        item_level: query.item_level,
+        selected_mods: vec![],
+        influences: query.influences.clone(),
+        cluster_enchant: query.cluster_enchant.clone(),
+    };
+    let available_mods = repo.find_mods(&available_mods_query);
+    if let Some(mod_ids) = target.as_plain_mods() {
+        let selected_mods: Vec<ModItem> = available_mods
+            .iter()
+            .filter(|m| mod_ids.contains(&m.mod_key))
+            .cloned()
+            .collect();
+        if selected_mods.len() == mod_ids.len() {
+            let plain_query = ModsQuery {
+                selected_mods,
+                ..query.clone()
+            };
+            return calculate_estimation_for_craft(repo, &plain_query);
+        }
+    }
+    let category = repo.get_item_category(&query.item_base);
+    rare_affix_limits(category)?;
+    let matcher = ModMatcher::new(target.clone(), &query.item_base, repo)?;
+    let probability = simulate_chaos_for_matcher(repo, query, &matcher, is_stale)?;
+    let target_mod_ids = target.mod_ids();
+    let target_weights = available_mods
+        .iter()
+        .filter(|m| target_mod_ids.contains(&m.mod_key))
+        .map(|m| {
+            let counted = get_target_and_better_mods(repo, query, &m.mod_key);
+            explain_target_weight(m, &counted, &available_mods)
         })
-        .sum();
-    Ok(Estimation { probability: sum })
+        .collect();
+    Ok(estimation(
+        probability,
+        vec![],
+        &available_mods,
+        target_weights,
+        category,
+    ))
+}
+
+/// Probabilities of one alteration for magic targets.
+/// `None` means there is no target of that affix type.
+/// Returns (alteration only, alteration with augmentation, augmentation usage)
+fn alteration_probabilities(p_prefix: Option<f64>, p_suffix: Option<f64>) -> (f64, f64, f64) {
+    let (mut alteration, mut with_augmentation, mut augmentation_usage) = (0.0, 0.0, 0.0);
+    // magic items of every category follow the same rules
+    for (pc, sc, ratio) in affix_count_variants(ItemCategory::default(), Rarity::Magic) {
+        let (pc, sc, ratio) = (*pc, *sc, *ratio);
+        // chance that rolled affixes hit their targets, open slot is filled later by augmentation
+        let rolled_hit = |count: usize, p: Option<f64>| match (count, p) {
+            (1, Some(p)) => p,
+            _ => 1.0,
+        };
+        let hit = rolled_hit(pc, p_prefix) * rolled_hit(sc, p_suffix);
+        let open_slot_target = match (pc, sc) {
+            (1, 0) => p_suffix,
+            (0, 1) => p_prefix,
+            _ => None,
+        };
+        match open_slot_target {
+            Some(p) => {
+                augmentation_usage += ratio * hit;
+                with_augmentation += ratio * hit * p;
+            }
+            None => {
+                alteration += ratio * hit;
+                with_augmentation += ratio * hit;
+            }
+        }
+    }
+    (alteration, with_augmentation, augmentation_usage)
+}
+
+pub fn calculate_magic_estimation(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+) -> Result<MagicEstimation, String> {
+    if query.selected_mods.is_empty() {
+        return Err("no mods selected".to_string());
+    }
+    // the first prefix and suffix are rolled on magic item, the rest one is added by regal
+    let mut magic_targets: Vec<ModItem> = vec![];
+    let mut regal_targets: Vec<ModItem> = vec![];
+    for m in query.selected_mods.iter() {
+        if magic_targets
+            .iter()
+            .any(|t| t.generation_type == m.generation_type)
+        {
+            regal_targets.push(m.clone());
+        } else {
+            magic_targets.push(m.clone());
+        }
+    }
+    if regal_targets.len() > 1 {
+        return Err("too many affixes selected for magic item".to_string());
+    }
+    if !regal_targets.is_empty() {
+        rare_affix_limits(repo.get_item_category(&query.item_base))?;
+    }
+
+    let available_mods_query = ModsQuery {
+        string_query: "".to_string(),
+        item_base: query.item_base.clone(),
+        item_level: query.item_level,
+        selected_mods: vec![],
+        influences: query.influences.clone(),
+        cluster_enchant: query.cluster_enchant.clone(),
+    };
+    let available_mods = repo.find_mods(&available_mods_query);
+    let pool_weight = |gen_type: &str| -> u32 {
+        available_mods
+            .iter()
+            .filter(|m| m.generation_type == gen_type)
+            .map(|m| m.weight)
+            .sum()
+    };
+    let target_probability = |gen_type: &str| -> Option<f64> {
+        magic_targets
+            .iter()
+            .find(|m| m.generation_type == gen_type)
+            .map(|m| {
+                get_weight_of_target_and_better_mods(repo, query, m.mod_key.clone()) as f64
+                    / pool_weight(gen_type) as f64
+            })
+    };
+    let (alteration_probability, augmentation_probability, augmentation_usage) =
+        alteration_probabilities(target_probability("prefix"), target_probability("suffix"));
+    debug!(
+        "magic: alt={}, alt+aug={}, aug usage={}",
+        alteration_probability, augmentation_probability, augmentation_usage
+    );
+    if augmentation_probability == 0.0 {
+        return Err("target mods can't be rolled on magic item".to_string());
+    }
+
+    let regal_probability = regal_targets.first().map(|regal_target| {
+        let magic_query = ModsQuery {
+            selected_mods: magic_targets.clone(),
+            ..query.clone()
+        };
+        let taken_weight = get_affected_weight_of_target_mod(repo, &magic_query);
+        let open_weight =
+            (pool_weight("prefix") + pool_weight("suffix")).saturating_sub(taken_weight);
+        get_weight_of_target_and_better_mods(repo, query, regal_target.mod_key.clone()) as f64
+            / open_weight as f64
+    });
+
+    Ok(MagicEstimation {
+        alteration_probability,
+        augmentation_probability,
+        augmentation_usage,
+        expected_alterations: 1.0 / augmentation_probability,
+        expected_augmentations: augmentation_usage / augmentation_probability,
+        regal_probability,
+    })
+}
+
+/// Chaos and harvest reforge probabilities of target, per target mod too
+pub fn calculate_reforge_estimation(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    target: &TargetExpr,
+    reforge: &Reforge,
+    is_stale: &dyn Fn() -> bool,
+) -> Result<ReforgeEstimation, String> {
+    check_rollable_target(target)?;
+    let matcher = ModMatcher::new(target.clone(), &query.item_base, repo)?;
+    let pool = build_pool(repo, query);
+    if !pool
+        .prefixes
+        .iter()
+        .chain(pool.suffixes.iter())
+        .any(|m| m.tags.contains(&reforge.tag))
+    {
+        return Err(format!("no {} mods can be rolled on item", reforge.tag));
+    }
+    let chaos = simulate_rolls(&pool, &matcher, None, DEFAULT_SIMULATION_TRIES, is_stale)?;
+    let reforged = simulate_rolls(
+        &pool,
+        &matcher,
+        Some(reforge),
+        DEFAULT_SIMULATION_TRIES,
+        is_stale,
+    )?;
+    let per_target = chaos
+        .probability_by_mod_id
+        .iter()
+        .sorted_by_key(|(mod_key, _)| mod_key.to_string())
+        .map(|(mod_key, p)| TargetProbability {
+            mod_key: mod_key.clone(),
+            chaos: *p,
+            reforge: reforged
+                .probability_by_mod_id
+                .get(mod_key)
+                .copied()
+                .unwrap_or(0.0),
+        })
+        .collect();
+    Ok(ReforgeEstimation {
+        chaos_probability: chaos.probability,
+        reforge_probability: reforged.probability,
+        per_target,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Some(0.1), None, (0.075, 0.1, 0.25))]
+    #[case(Some(0.1), Some(0.2), (0.01, 0.02, 0.075))]
+    fn test_alteration_probabilities(
+        #[case] p_prefix: Option<f64>,
+        #[case] p_suffix: Option<f64>,
+        #[case] expected: (f64, f64, f64),
+    ) {
+        let (alteration, with_augmentation, augmentation_usage) =
+            alteration_probabilities(p_prefix, p_suffix);
+        assert!((alteration - expected.0).abs() < 1e-9);
+        assert!((with_augmentation - expected.1).abs() < 1e-9);
+        assert!((augmentation_usage - expected.2).abs() < 1e-9);
+    }
+
+    fn mod_item(mod_key: &str, family: &str, generation_type: &str, tier: usize) -> ModItem {
+        ModItem {
+            generation_type: generation_type.to_string(),
+            family: family.to_string(),
+            groups: vec![family.to_string()],
+            tier,
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    #[rstest]
+    #[case(3, 1, 0.25)]
+    #[case(3, 2, 0.25 + 1000.0 / 3600.0)]
+    #[case(1, 3, 0.25 + 1000.0 / 3600.0 + 1000.0 / 3240.0)]
+    fn test_probability_for_suffix_target(
+        #[case] prefix_count: usize,
+        #[case] suffix_count: usize,
+        #[case] expected: f64,
+    ) {
+        let available_mods = vec![
+            ModItem {
+                weight: 1000,
+                ..mod_item("IncreasedLife4", "IncreasedLife", "prefix", 1)
+            },
+            ModItem {
+                weight: 1000,
+                ..mod_item("FireResist3", "FireResist", "suffix", 1)
+            },
+            ModItem {
+                weight: 1000,
+                ..mod_item("ColdResist3", "ColdResist", "suffix", 1)
+            },
+            ModItem {
+                weight: 2000,
+                ..mod_item("LightningResist3", "LightningResist", "suffix", 1)
+            },
+        ];
+        let selected = vec![(available_mods[1].clone(), 1000)];
+        let p = probability_for_variant(
+            prefix_count,
+            suffix_count,
+            &selected,
+            &available_mods,
+            &|m: &ModItem| m.weight,
+        );
+        assert!((p - expected).abs() < 1e-9, "{} != {}", p, expected);
+    }
+
+    #[rstest]
+    fn test_explain_target_weight() {
+        let pool = vec![
+            mod_item("IncreasedLife3", "IncreasedLife", "prefix", 3),
+            mod_item("IncreasedLife5", "IncreasedLife", "prefix", 1),
+            mod_item("IncreasedLife4", "IncreasedLife", "prefix", 2),
+            mod_item("IncreasedMana4", "IncreasedMana", "prefix", 1),
+            mod_item("FireResist3", "FireResist", "suffix", 1),
+        ];
+        let counted = vec![
+            ("IncreasedLife4".to_string(), 1000),
+            ("IncreasedLife5".to_string(), 500),
+        ];
+        let explained = explain_target_weight(&pool[2], &counted, &pool);
+        assert_eq!(explained.weight, 1500);
+        assert_eq!(explained.pool_weight, 4000);
+        assert_eq!(
+            explained.counted_mods,
+            vec!["IncreasedLife5", "IncreasedLife4"]
+        );
+    }
 }
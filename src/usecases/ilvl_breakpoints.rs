@@ -0,0 +1,130 @@
+use itertools::Itertools;
+
+use crate::entities::craft_repo::{
+    CraftRepo, IlvlBreakpoint, IlvlReport, ModItem, ModsQuery, TierUnlock,
+};
+use crate::usecases::estimation::calculate_estimation_for_craft;
+
+pub const MAX_ITEM_LEVEL: u64 = 100;
+
+/// Tiers of target families with item level they unlock at, lowest level first
+pub fn tier_unlocks(pool: &[ModItem], targets: &[ModItem]) -> Vec<TierUnlock> {
+    pool.iter()
+        .filter(|m| {
+            targets
+                .iter()
+                .any(|t| t.family == m.family && t.generation_type == m.generation_type)
+        })
+        .map(|m| TierUnlock {
+            family: m.family.clone(),
+            tier: m.tier,
+            mod_key: m.mod_key.clone(),
+            item_level: m.required_level.max(1),
+        })
+        .sorted_by(|a, b| {
+            a.item_level
+                .cmp(&b.item_level)
+                .then_with(|| a.family.cmp(&b.family))
+        })
+        .collect()
+}
+
+/// Item levels where pool of base changes, chance stays the same between them
+pub fn breakpoint_levels(pool: &[ModItem]) -> Vec<u64> {
+    pool.iter()
+        .map(|m| m.required_level.clamp(1, MAX_ITEM_LEVEL))
+        .chain([1])
+        .unique()
+        .sorted()
+        .collect()
+}
+
+/// Chance to hit selected mods or better ones at every item level breakpoint of base
+pub fn analyze_item_levels(repo: &impl CraftRepo, query: &ModsQuery) -> Result<IlvlReport, String> {
+    if query.selected_mods.is_empty() {
+        return Err("no mods selected".to_string());
+    }
+    let pool = repo.find_mods(&ModsQuery {
+        string_query: "".to_string(),
+        item_level: MAX_ITEM_LEVEL,
+        selected_mods: vec![],
+        ..query.clone()
+    });
+    let tiers = tier_unlocks(&pool, &query.selected_mods);
+    let mut breakpoints = vec![];
+    for item_level in breakpoint_levels(&pool) {
+        let reachable = query
+            .selected_mods
+            .iter()
+            .all(|m| m.required_level <= item_level);
+        let probability = match reachable {
+            true => {
+                let level_query = ModsQuery {
+                    string_query: "".to_string(),
+                    item_level,
+                    ..query.clone()
+                };
+                calculate_estimation_for_craft(repo, &level_query)?.probability
+            }
+            false => 0.0,
+        };
+        breakpoints.push(IlvlBreakpoint {
+            item_level,
+            probability,
+            unlocked: tiers
+                .iter()
+                .filter(|t| t.item_level == item_level)
+                .map(|t| t.mod_key.clone())
+                .collect(),
+        });
+    }
+    Ok(IlvlReport { tiers, breakpoints })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn mod_item(mod_key: &str, family: &str, tier: usize, required_level: u64) -> ModItem {
+        ModItem {
+            required_level,
+            family: family.to_string(),
+            groups: vec![family.to_string()],
+            tier,
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn pool() -> Vec<ModItem> {
+        vec![
+            mod_item("IncreasedLife1", "IncreasedLife", 3, 0),
+            mod_item("IncreasedLife3", "IncreasedLife", 2, 24),
+            mod_item("IncreasedLife7", "IncreasedLife", 1, 86),
+            mod_item("LocalIncreasedPhysicalDamage4", "PhysicalDamage", 1, 46),
+            mod_item("ColdResist2", "ColdResist", 1, 24),
+        ]
+    }
+
+    #[rstest]
+    fn test_tier_unlocks() {
+        let targets = vec![mod_item("IncreasedLife3", "IncreasedLife", 2, 24)];
+        let unlocks: Vec<(String, u64)> = tier_unlocks(&pool(), &targets)
+            .into_iter()
+            .map(|t| (t.mod_key, t.item_level))
+            .collect();
+        assert_eq!(
+            unlocks,
+            vec![
+                ("IncreasedLife1".to_string(), 1),
+                ("IncreasedLife3".to_string(), 24),
+                ("IncreasedLife7".to_string(), 86),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_breakpoint_levels() {
+        assert_eq!(breakpoint_levels(&pool()), vec![1, 24, 46, 86]);
+    }
+}
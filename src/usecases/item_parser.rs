@@ -1,7 +1,9 @@
 use log::{debug, warn};
 use regex::Regex;
 
-use crate::entities::craft_repo::CraftRepo;
+use crate::entities::craft_repo::{
+    CraftRepo, ImplicitKind, ItemImplicit, ModHint, ModResolution, ModsQuery,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct ParsedItem {
@@ -10,6 +12,8 @@ pub struct ParsedItem {
     pub item_name: String,
     pub mods: Vec<String>,
     pub raw_mods: Vec<String>,
+    /// mods resolved with doubts, e.g. header and text point to different mods
+    pub warnings: Vec<String>,
 }
 
 fn fetch_item_class<'a>(craft_repo: &impl CraftRepo, raw_item: &'a str) -> Result<&'a str, String> {
@@ -94,12 +98,13 @@ struct RawModDTO {
     mod_text: Vec<String>,
     tags: Vec<String>,
     tier: Option<u32>,
+    warning: Option<String>,
 }
 
 impl RawModDTO {
     fn new(
         meta_info: ModMetaInfo,
-        mod_id: String,
+        resolution: ModResolution,
         mod_text: Vec<String>,
         mod_decription: Option<String>,
     ) -> Self {
@@ -108,9 +113,10 @@ impl RawModDTO {
             tier: meta_info.tier,
             tags: meta_info.tags,
             mod_name: meta_info.mod_name,
-            mod_id,
+            mod_id: resolution.mod_id,
             mod_text,
             mod_decription,
+            warning: resolution.warning,
         }
     }
 }
@@ -122,6 +128,24 @@ struct ModMetaInfo {
     mod_name: Option<String>,
 }
 
+impl ModMetaInfo {
+    /// Header details for mods with the same text, name is unquoted
+    fn hint(&self) -> ModHint {
+        ModHint {
+            generation_type: match self.generation_type {
+                ModGenerationTypeEnum::Prefix => Some("prefix".to_string()),
+                ModGenerationTypeEnum::Suffix => Some("suffix".to_string()),
+                ModGenerationTypeEnum::Other => None,
+            },
+            name: self
+                .mod_name
+                .as_ref()
+                .map(|n| n.trim_matches('"').to_string()),
+            tier: self.tier.map(|t| t as usize),
+        }
+    }
+}
+
 fn create_meta_mods_regexp_patter() -> Result<Regex, String> {
     let meta_mod_line_re =
         Regex::new(r"\{\s+(\w+)\s+Modifier\s+(.*?)\s+\(Tier:\s+(\d+)\)\s+(—\s+(.*?)(?:\s+\})|$)?")
@@ -129,8 +153,35 @@ fn create_meta_mods_regexp_patter() -> Result<Regex, String> {
     Ok(meta_mod_line_re)
 }
 
+/// Mod text with its header and description
+type ModLines = (ModMetaInfo, Vec<String>, Option<String>);
+
+/// Header resolves mod id, text is a fallback, their disagreement is kept as warning
+fn resolve_mods(
+    craft_repo: &impl CraftRepo,
+    item_dto: &ItemDTO,
+    mods: Vec<ModLines>,
+) -> Result<Vec<RawModDTO>, String> {
+    let queries: Vec<(String, ModHint)> = mods
+        .iter()
+        .map(|(meta, mod_text, _)| (mod_text.join("\n"), meta.hint()))
+        .collect();
+    let resolutions =
+        craft_repo.resolve_mods(item_dto.item_class, &item_dto.item_base_name, &queries)?;
+    Ok(mods
+        .into_iter()
+        .zip(resolutions)
+        .map(|((meta, mod_text, descr), resolution)| {
+            if let Some(warning) = &resolution.warning {
+                warn!("{}", warning);
+            }
+            RawModDTO::new(meta, resolution, mod_text, descr)
+        })
+        .collect())
+}
+
 fn fetch_mods(craft_repo: &impl CraftRepo, item_dto: ItemDTO) -> Result<Vec<RawModDTO>, String> {
-    let mut mods: Vec<RawModDTO> = vec![];
+    let mut mods: Vec<ModLines> = vec![];
     debug!("start parsing mods in {}", &item_dto.last_part);
 
     let meta_mod_line_re = create_meta_mods_regexp_patter()?;
@@ -148,13 +199,7 @@ fn fetch_mods(craft_repo: &impl CraftRepo, item_dto: ItemDTO) -> Result<Vec<RawM
                 // row contains meta info for mod
                 if let Some(last_mod_meta) = mod_meta {
                     // let's close prev cap and continue new one
-                    let mod_id = craft_repo.string_to_mod(
-                        &item_dto.item_class,
-                        &item_dto.item_base_name,
-                        &mod_text.join("\n"),
-                    )?;
-                    let mod_to_save = RawModDTO::new(last_mod_meta, mod_id, mod_text, descr);
-                    mods.push(mod_to_save);
+                    mods.push((last_mod_meta, mod_text, descr));
                 };
 
                 let curr_mod_meta = ModMetaInfo {
@@ -187,16 +232,10 @@ fn fetch_mods(craft_repo: &impl CraftRepo, item_dto: ItemDTO) -> Result<Vec<RawM
     }
     // close prev cap
     if let Some(last_mod_meta) = mod_meta {
-        let mod_id = craft_repo.string_to_mod(
-            &item_dto.item_class,
-            &item_dto.item_base_name,
-            &mod_text.join("\n"),
-        )?;
-        let mod_to_save = RawModDTO::new(last_mod_meta, mod_id, mod_text, descr);
-        mods.push(mod_to_save);
+        mods.push((last_mod_meta, mod_text, descr));
     };
 
-    Ok(mods)
+    resolve_mods(craft_repo, &item_dto, mods)
 }
 
 pub fn parse_raw_item(craft_repo: &impl CraftRepo, raw_item: &str) -> Result<ParsedItem, String> {
@@ -211,10 +250,119 @@ pub fn parse_raw_item(craft_repo: &impl CraftRepo, raw_item: &str) -> Result<Par
         item_base_name: item_dto.item_base_name,
         item_name: item_dto.item_name,
         mods: mods_dto.iter().map(|m| m.mod_id.to_owned()).collect(),
-        raw_mods: vec![],
+        raw_mods: mods_dto
+            .iter()
+            .map(|m| m.mod_text.join("\n").trim().to_owned())
+            .collect(),
+        warnings: mods_dto.iter().filter_map(|m| m.warning.clone()).collect(),
     })
 }
 
+/// Explicit mods of item with their generation type, "prefix" or "suffix"
+pub fn parse_item_affixes(
+    craft_repo: &impl CraftRepo,
+    raw_item: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let item_class = fetch_item_class(craft_repo, raw_item)?;
+    let item_dto = fetch_item_base(craft_repo, raw_item, item_class)?;
+    Ok(fetch_mods(craft_repo, item_dto)?
+        .into_iter()
+        .filter_map(|m| match m.generation_type {
+            ModGenerationTypeEnum::Prefix => Some((m.mod_id, "prefix".to_string())),
+            ModGenerationTypeEnum::Suffix => Some((m.mod_id, "suffix".to_string())),
+            ModGenerationTypeEnum::Other => None,
+        })
+        .collect())
+}
+
+/// Header of implicit in advanced item description,
+/// e.g. `{ Searing Exarch Implicit Modifier (Lesser) — Damage }`
+fn create_implicit_header_regexp() -> Regex {
+    Regex::new(r"^\{\s+(.*?)\s*Implicit Modifier").expect("regexp error during implicit fetching")
+}
+
+fn implicit_kind_by_header(header: &str) -> ImplicitKind {
+    if header.contains("Searing Exarch") || header.contains("Eater of Worlds") {
+        ImplicitKind::Eldritch
+    } else if header.contains("Corrupt") {
+        ImplicitKind::Corrupted
+    } else if header.contains("Synthes") {
+        ImplicitKind::Synthesis
+    } else {
+        ImplicitKind::Implicit
+    }
+}
+
+/// Texts of implicits and enchants with kind by header or marker.
+/// Lines under one header are one mod, every line is a mod without headers
+fn collect_implicit_texts(raw_item: &str) -> Vec<(ImplicitKind, String)> {
+    let header_re = create_implicit_header_regexp();
+    let mut res: Vec<(ImplicitKind, Vec<String>)> = vec![];
+    let mut header_kind: Option<ImplicitKind> = None;
+    let mut header_has_lines = false;
+    for row in raw_item.split("\n").map(|r| r.trim()) {
+        if let Some(c) = header_re.captures(row) {
+            header_kind = Some(implicit_kind_by_header(&c[1]));
+            header_has_lines = false;
+            continue;
+        }
+        if let Some(text) = row.strip_suffix("(enchant)") {
+            res.push((ImplicitKind::Enchant, vec![text.trim().to_string()]));
+            continue;
+        }
+        let text = match row.strip_suffix("(implicit)") {
+            Some(text) => text.trim().to_string(),
+            None => {
+                header_kind = None;
+                continue;
+            }
+        };
+        match (header_kind, header_has_lines) {
+            (Some(_), true) => res.last_mut().unwrap().1.push(text),
+            (Some(kind), false) => {
+                res.push((kind, vec![text]));
+                header_has_lines = true;
+            }
+            (None, _) => res.push((ImplicitKind::Implicit, vec![text])),
+        }
+    }
+    res.into_iter()
+        .map(|(kind, lines)| (kind, lines.join("\n")))
+        .collect()
+}
+
+/// Implicits and enchants of item, kind is taken from repo's mod when its text is known
+pub fn parse_item_implicits(
+    craft_repo: &impl CraftRepo,
+    raw_item: &str,
+) -> Result<Vec<ItemImplicit>, String> {
+    let item_class = fetch_item_class(craft_repo, raw_item)?;
+    let item_dto = fetch_item_base(craft_repo, raw_item, item_class)?;
+    let pool = craft_repo.find_implicit_mods(&ModsQuery {
+        string_query: "".to_string(),
+        item_level: 100,
+        item_base: item_dto.item_base_name.clone(),
+        selected_mods: vec![],
+        influences: vec![],
+        cluster_enchant: None,
+    });
+    Ok(collect_implicit_texts(raw_item)
+        .into_iter()
+        .map(|(kind, text)| {
+            let mod_id = craft_repo
+                .string_to_implicit(&item_dto.item_base_name, &text)
+                .map_err(|e| warn!("{}", e))
+                .ok();
+            let kind = mod_id
+                .as_ref()
+                .and_then(|id| pool.iter().find(|m| &m.mod_key == id))
+                .and_then(|m| ImplicitKind::from_generation_type(&m.generation_type))
+                .unwrap_or(kind);
+            ItemImplicit { kind, text, mod_id }
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,6 +397,43 @@ mod tests {
         assert_eq!(cap.is_none(), true)
     }
 
+    #[rstest]
+    fn test_collect_implicit_texts() {
+        let raw_item = "Item Class: Boots
+Rarity: Rare
+--------
+Adds 1 Passive Skill (enchant)
+--------
+{ Implicit Modifier — Life }
++25(20-30) to maximum Life (implicit)
+{ Searing Exarch Implicit Modifier (Lesser) — Damage }
+5% increased Damage (implicit)
+Adds 1 to 2 Fire Damage (implicit)
+--------
+{ Prefix Modifier \"Hale\" (Tier: 9) — Life }
++5(3-9) to maximum Life";
+        let texts = collect_implicit_texts(raw_item);
+        assert_eq!(
+            texts,
+            vec![
+                (ImplicitKind::Enchant, "Adds 1 Passive Skill".to_string()),
+                (
+                    ImplicitKind::Implicit,
+                    "+25(20-30) to maximum Life".to_string()
+                ),
+                (
+                    ImplicitKind::Eldritch,
+                    "5% increased Damage\nAdds 1 to 2 Fire Damage".to_string()
+                ),
+            ]
+        );
+        // simple item description has no headers
+        let texts = collect_implicit_texts(
+            "+1 to Level of Socketed Gems (implicit)\n+10 to Strength (implicit)",
+        );
+        assert_eq!(texts.len(), 2);
+    }
+
     #[rstest]
     fn test_fetching_mods(repo: impl CraftRepo) {
         let item_dto = ItemDTO{
@@ -271,6 +456,7 @@ mod tests {
             mod_name: Some("\"Remora\'s\"".to_owned()),
             mod_text: vec!["0.26(0.2-0.4)% of Physical Attack Damage Leeched as Life".to_owned()],
             mod_decription: Some("(Leeched Life is recovered over time. Multiple Leeches can occur simultaneously, up to a maximum rate)".to_owned()),
+            warning: None,
         };
         assert_eq!(mods[0], expected_mod1);
 
@@ -286,6 +472,7 @@ mod tests {
             mod_name: Some("\"of the Seal\"".to_owned()),
             mod_text: vec!["+12(12-17)% to Cold Resistance".to_owned()],
             mod_decription: None,
+            warning: None,
         };
 
         assert_eq!(mods[1], expected_mod2);
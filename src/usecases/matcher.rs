@@ -1,45 +1,342 @@
-use crate::entities::craft_repo::CraftRepo;
-use log::{debug, info};
+use crate::entities::craft_repo::{AffixLimits, CraftRepo, Influence, ModsQuery};
+use crate::entities::target::{PseudoStat, TargetExpr};
+use crate::usecases::rules::max_affix_limits;
+use log::debug;
 use std::collections::{HashMap, HashSet};
+
+/// Crafted item as seen by matcher
+#[derive(Debug, Clone, Default)]
+pub struct CraftedItem {
+    pub mod_ids: HashSet<String>,
+    pub pseudo_stats: HashMap<PseudoStat, f64>,
+    /// implicits and enchants, empty for simulated items
+    pub implicit_ids: HashSet<String>,
+}
+
+impl CraftedItem {
+    pub fn from_mod_ids(mod_ids: HashSet<String>) -> CraftedItem {
+        CraftedItem {
+            mod_ids,
+            ..CraftedItem::default()
+        }
+    }
+}
+
+/// Error for target which rolls can't reach,
+/// simulated items have no implicits so implicit checks would be always false
+pub fn check_rollable_target(target: &TargetExpr) -> Result<(), String> {
+    if target.uses_implicits() {
+        return Err(
+            "implicit targets are checked on pasted item only, remove them to estimate".to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[derive(Clone)]
 pub struct ModMatcher {
+    pub target: TargetExpr,
     pub accepted_modset_by_mod_id: HashMap<String, HashSet<String>>,
+    pub generation_type_by_mod_id: HashMap<String, String>,
+    pub max_prefix_count: usize,
+    pub max_suffix_count: usize,
 }
 
 impl ModMatcher {
     pub fn new(
-        selected_mods: HashSet<String>,
+        target: TargetExpr,
         item_base_name: &str,
         repo: &impl CraftRepo,
     ) -> Result<ModMatcher, String> {
         let mut accepted_modset_by_mod_id = HashMap::new();
 
-        for m_id in selected_mods.into_iter() {
+        for m_id in target.mod_ids().into_iter() {
             let subset = repo.get_subset_of_mods(&m_id, item_base_name)?;
             debug!("Got subset: {:?}", &subset);
-            accepted_modset_by_mod_id
-                .insert(m_id, subset);
+            accepted_modset_by_mod_id.insert(m_id, subset);
         }
+        let query = ModsQuery {
+            string_query: "".to_string(),
+            item_base: item_base_name.to_string(),
+            item_level: 100,
+            selected_mods: vec![],
+            influences: Influence::ALL.to_vec(),
+            cluster_enchant: None,
+        };
+        let generation_type_by_mod_id = repo
+            .find_mods(&query)
+            .into_iter()
+            .map(|m| (m.mod_key, m.generation_type))
+            .collect();
+        let limits = max_affix_limits(repo.get_item_category(item_base_name));
         Ok(ModMatcher {
+            target,
             accepted_modset_by_mod_id,
+            generation_type_by_mod_id,
+            max_prefix_count: limits.prefixes,
+            max_suffix_count: limits.suffixes,
         })
     }
-}
 
-pub fn check_matching(matcher: ModMatcher, crafted_mod_ids: HashSet<String>) -> bool {
-    for (_, accepted_set) in matcher.accepted_modset_by_mod_id {
-        let mut matched = false;
-        debug!("Looking for: {:?}", &accepted_set);
-        for crafted_mod_id in &crafted_mod_ids {
-            if accepted_set.contains(crafted_mod_id) {
-                debug!("matched {}", crafted_mod_id);
-                matched = true;
-            } else {
-                debug!("match failed {}", crafted_mod_id);
-            }
+    /// Affix caps of item, e.g. 1 prefix and 1 suffix for magic items
+    pub fn with_affix_limits(self, limits: AffixLimits) -> ModMatcher {
+        ModMatcher {
+            max_prefix_count: limits.prefixes,
+            max_suffix_count: limits.suffixes,
+            ..self
         }
-        if !matched {
+    }
+
+    /// Whether one more affix of open type may complete the target,
+    /// used to decide if augmentation worth to be applied on magic item
+    pub fn worth_to_augment(&self, crafted: &CraftedItem) -> bool {
+        if crafted.mod_ids.len() != 1 || check_matching(self, crafted) {
             return false;
         }
+        let open_gen_type = match self.count_of_generation_type(&crafted.mod_ids, "prefix") {
+            0 => "prefix",
+            _ => "suffix",
+        };
+        let target_mod_ids = self.target.mod_ids();
+        let is_open_type = |m: &String| {
+            self.generation_type_by_mod_id
+                .get(m)
+                .is_some_and(|g| g == open_gen_type)
+        };
+        let open_type_targeted = target_mod_ids.iter().any(is_open_type);
+        let rolled_type_targeted = target_mod_ids.iter().any(|m| !is_open_type(m));
+        let rolled_is_useful = target_mod_ids
+            .iter()
+            .filter_map(|m| self.accepted_modset_by_mod_id.get(m))
+            .any(|accepted| crafted.mod_ids.iter().any(|m| accepted.contains(m)));
+        open_type_targeted && (rolled_is_useful || !rolled_type_targeted)
+    }
+
+    fn count_of_generation_type(&self, crafted_mod_ids: &HashSet<String>, gen_type: &str) -> usize {
+        crafted_mod_ids
+            .iter()
+            .filter(|m| {
+                self.generation_type_by_mod_id
+                    .get(*m)
+                    .is_some_and(|g| g == gen_type)
+            })
+            .count()
+    }
+
+    /// Whether item has target mod or any better tier of it
+    pub fn is_mod_matched(&self, mod_id: &str, crafted: &CraftedItem) -> bool {
+        let accepted_set = match self.accepted_modset_by_mod_id.get(mod_id) {
+            Some(s) => s,
+            None => return false,
+        };
+        debug!("Looking for: {:?}", accepted_set);
+        let matched = crafted.mod_ids.iter().any(|m| accepted_set.contains(m));
+        debug!("matched {}: {}", mod_id, matched);
+        matched
+    }
+
+    fn evaluate(&self, expr: &TargetExpr, crafted: &CraftedItem) -> bool {
+        match expr {
+            TargetExpr::Mod { mod_id } => self.is_mod_matched(mod_id, crafted),
+            TargetExpr::And { exprs } => exprs.iter().all(|e| self.evaluate(e, crafted)),
+            TargetExpr::Or { exprs } => exprs.iter().any(|e| self.evaluate(e, crafted)),
+            TargetExpr::Not { expr } => !self.evaluate(expr, crafted),
+            TargetExpr::AtLeast { count, exprs } => {
+                exprs.iter().filter(|e| self.evaluate(e, crafted)).count() >= *count
+            }
+            TargetExpr::OpenPrefixes { count } => {
+                let used = self.count_of_generation_type(&crafted.mod_ids, "prefix");
+                self.max_prefix_count.saturating_sub(used) >= *count
+            }
+            TargetExpr::OpenSuffixes { count } => {
+                let used = self.count_of_generation_type(&crafted.mod_ids, "suffix");
+                self.max_suffix_count.saturating_sub(used) >= *count
+            }
+            TargetExpr::Pseudo { stat, min } => {
+                crafted.pseudo_stats.get(stat).copied().unwrap_or(0.0) >= *min
+            }
+            TargetExpr::Implicit { mod_id } => crafted.implicit_ids.contains(mod_id),
+        }
+    }
+}
+
+pub fn check_matching(matcher: &ModMatcher, crafted: &CraftedItem) -> bool {
+    matcher.evaluate(&matcher.target, crafted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usecases::rules::MAGIC_LIMITS;
+    use rstest::rstest;
+
+    fn matcher(target: TargetExpr) -> ModMatcher {
+        ModMatcher {
+            target,
+            accepted_modset_by_mod_id: HashMap::from([
+                (
+                    "IncreasedLife4".to_string(),
+                    HashSet::from(["IncreasedLife4".to_string(), "IncreasedLife5".to_string()]),
+                ),
+                (
+                    "MovementVelocity3".to_string(),
+                    HashSet::from(["MovementVelocity3".to_string()]),
+                ),
+                (
+                    "FireResist3".to_string(),
+                    HashSet::from(["FireResist3".to_string()]),
+                ),
+            ]),
+            generation_type_by_mod_id: HashMap::from([
+                ("IncreasedLife4".to_string(), "prefix".to_string()),
+                ("IncreasedLife5".to_string(), "prefix".to_string()),
+                ("MovementVelocity3".to_string(), "prefix".to_string()),
+                ("FireResist3".to_string(), "suffix".to_string()),
+            ]),
+            max_prefix_count: 3,
+            max_suffix_count: 3,
+        }
+    }
+
+    fn mod_expr(mod_id: &str) -> TargetExpr {
+        TargetExpr::Mod {
+            mod_id: mod_id.to_string(),
+        }
+    }
+
+    #[rstest]
+    #[case(vec!["IncreasedLife5", "MovementVelocity3"], true)]
+    #[case(vec!["IncreasedLife5", "FireResist3"], true)]
+    #[case(vec!["MovementVelocity3", "FireResist3"], false)]
+    fn test_or_of_ands(#[case] crafted: Vec<&str>, #[case] expected: bool) {
+        let target = TargetExpr::Or {
+            exprs: vec![
+                TargetExpr::And {
+                    exprs: vec![mod_expr("IncreasedLife4"), mod_expr("MovementVelocity3")],
+                },
+                TargetExpr::And {
+                    exprs: vec![mod_expr("IncreasedLife4"), mod_expr("FireResist3")],
+                },
+            ],
+        };
+        let crafted =
+            CraftedItem::from_mod_ids(HashSet::from_iter(crafted.into_iter().map(String::from)));
+        assert_eq!(check_matching(&matcher(target), &crafted), expected);
+    }
+
+    #[rstest]
+    #[case(vec!["IncreasedLife4"], false)]
+    #[case(vec!["IncreasedLife4", "FireResist3"], true)]
+    #[case(vec!["IncreasedLife4", "MovementVelocity3", "FireResist3"], true)]
+    fn test_at_least(#[case] crafted: Vec<&str>, #[case] expected: bool) {
+        let target = TargetExpr::AtLeast {
+            count: 2,
+            exprs: vec![
+                mod_expr("IncreasedLife4"),
+                mod_expr("MovementVelocity3"),
+                mod_expr("FireResist3"),
+            ],
+        };
+        let crafted =
+            CraftedItem::from_mod_ids(HashSet::from_iter(crafted.into_iter().map(String::from)));
+        assert_eq!(check_matching(&matcher(target), &crafted), expected);
+    }
+
+    #[rstest]
+    #[case(vec!["IncreasedLife4"], true)]
+    #[case(vec!["IncreasedLife4", "MovementVelocity3"], false)]
+    #[case(vec!["IncreasedLife4", "FireResist3"], false)]
+    fn test_not_and_open_prefixes(#[case] crafted: Vec<&str>, #[case] expected: bool) {
+        let target = TargetExpr::And {
+            exprs: vec![
+                TargetExpr::Not {
+                    expr: Box::new(mod_expr("FireResist3")),
+                },
+                TargetExpr::OpenPrefixes { count: 2 },
+            ],
+        };
+        let crafted =
+            CraftedItem::from_mod_ids(HashSet::from_iter(crafted.into_iter().map(String::from)));
+        assert_eq!(check_matching(&matcher(target), &crafted), expected);
+    }
+
+    #[rstest]
+    #[case(80.0, true)]
+    #[case(90.0, false)]
+    fn test_pseudo(#[case] min: f64, #[case] expected: bool) {
+        let target = TargetExpr::Pseudo {
+            stat: PseudoStat::TotalResistance,
+            min,
+        };
+        let crafted = CraftedItem {
+            pseudo_stats: HashMap::from([(PseudoStat::TotalResistance, 85.0)]),
+            ..CraftedItem::default()
+        };
+        assert_eq!(check_matching(&matcher(target), &crafted), expected);
+    }
+
+    #[rstest]
+    #[case(vec!["EnchantmentBootsMovementVelocity2"], true)]
+    #[case(vec![], false)]
+    fn test_implicit(#[case] implicits: Vec<&str>, #[case] expected: bool) {
+        let target = TargetExpr::And {
+            exprs: vec![
+                mod_expr("IncreasedLife4"),
+                TargetExpr::Implicit {
+                    mod_id: "EnchantmentBootsMovementVelocity2".to_string(),
+                },
+            ],
+        };
+        let crafted = CraftedItem {
+            mod_ids: HashSet::from(["IncreasedLife4".to_string()]),
+            implicit_ids: implicits.into_iter().map(|m| m.to_string()).collect(),
+            ..CraftedItem::default()
+        };
+        assert_eq!(check_matching(&matcher(target), &crafted), expected);
+    }
+
+    #[rstest]
+    #[case(vec!["IncreasedLife5"], true)]
+    #[case(vec!["MovementVelocity3"], false)]
+    #[case(vec!["IncreasedLife5", "FireResist3"], false)]
+    fn test_worth_to_augment(#[case] crafted: Vec<&str>, #[case] expected: bool) {
+        let target = TargetExpr::And {
+            exprs: vec![mod_expr("IncreasedLife4"), mod_expr("FireResist3")],
+        };
+        let crafted =
+            CraftedItem::from_mod_ids(HashSet::from_iter(crafted.into_iter().map(String::from)));
+        let matcher = matcher(target).with_affix_limits(MAGIC_LIMITS);
+        assert_eq!(matcher.worth_to_augment(&crafted), expected);
+    }
+
+    #[rstest]
+    fn test_target_json_roundtrip() {
+        let target = TargetExpr::AtLeast {
+            count: 1,
+            exprs: vec![
+                mod_expr("IncreasedLife4"),
+                TargetExpr::OpenSuffixes { count: 1 },
+            ],
+        };
+        assert_eq!(TargetExpr::from_json(&target.to_json()), Ok(target));
+    }
+
+    #[rstest]
+    fn test_check_rollable_target() {
+        let implicit = || TargetExpr::Implicit {
+            mod_id: "Corrupted".to_string(),
+        };
+        let life = || TargetExpr::Mod {
+            mod_id: "IncreasedLife4".to_string(),
+        };
+        assert!(check_rollable_target(&life()).is_ok());
+        let not_implicit = TargetExpr::Not {
+            expr: Box::new(implicit()),
+        };
+        assert!(check_rollable_target(&not_implicit).is_err());
+        let with_implicit = TargetExpr::And {
+            exprs: vec![life(), implicit()],
+        };
+        assert!(check_rollable_target(&with_implicit).is_err());
     }
-    true
 }
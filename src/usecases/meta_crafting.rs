@@ -0,0 +1,194 @@
+use itertools::Itertools;
+use std::collections::HashSet;
+
+use crate::entities::craft_repo::{
+    AffixLimits, CraftRepo, MetaCraft, MetaCraftEstimation, ModsQuery, MULTIPLE_CRAFTED_MODS_KEY,
+};
+use crate::entities::target::TargetExpr;
+use crate::usecases::craft_searcher::get_bench_crafts;
+use crate::usecases::currency::{cost_in_chaos, currency_price, CHAOS, SCOUR};
+use crate::usecases::matcher::{check_matching, check_rollable_target, CraftedItem, ModMatcher};
+use crate::usecases::rules::rare_affix_limits;
+use crate::usecases::simulation::{
+    build_pool, simulate_meta_rolls, MetaRoll, DEFAULT_SIMULATION_TRIES,
+};
+
+/// Scouring orb with locked side clears the other side with crafts on it,
+/// locked mods and crafts of locked side stay: whether item hits target then
+fn scour_hits_target(
+    locked_mod_ids: HashSet<String>,
+    locked_side: &str,
+    locked_side_crafts: usize,
+    limits: AffixLimits,
+    matcher: &ModMatcher,
+) -> bool {
+    let limits = match locked_side {
+        "prefix" => AffixLimits {
+            prefixes: limits.prefixes.saturating_sub(locked_side_crafts),
+            ..limits
+        },
+        _ => AffixLimits {
+            suffixes: limits.suffixes.saturating_sub(locked_side_crafts),
+            ..limits
+        },
+    };
+    let matcher = matcher.clone().with_affix_limits(limits);
+    check_matching(&matcher, &CraftedItem::from_mod_ids(locked_mod_ids))
+}
+
+/// Chaos spam on item with meta-crafts. Selected mods of locked side are expected
+/// to be on item already, meta-crafts are crafted again after every chaos orb.
+/// With locked side, single scouring orb instead of chaos spam is estimated too
+pub fn calculate_meta_craft_estimation(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    target: &TargetExpr,
+    meta_crafts: &[MetaCraft],
+    is_stale: &dyn Fn() -> bool,
+) -> Result<MetaCraftEstimation, String> {
+    if meta_crafts.is_empty() {
+        return Err("no meta-crafts selected".to_string());
+    }
+    check_rollable_target(target)?;
+    let locked_sides: Vec<&str> = meta_crafts
+        .iter()
+        .filter_map(|m| m.locked_side())
+        .unique()
+        .collect();
+    if locked_sides.len() > 1 {
+        return Err("prefixes and suffixes can't be locked together".to_string());
+    }
+    let locked_side = locked_sides.first().map(|s| s.to_string());
+
+    let mut mod_keys: Vec<&str> = meta_crafts.iter().map(|m| m.mod_key()).collect();
+    if meta_crafts.len() > 1 {
+        mod_keys.push(MULTIPLE_CRAFTED_MODS_KEY);
+    }
+    let bench_crafts = get_bench_crafts(repo, &query.item_base);
+    let mut crafts = vec![];
+    for mod_key in mod_keys {
+        match bench_crafts.iter().find(|c| c.mod_key == mod_key) {
+            Some(c) => crafts.push(c),
+            None => {
+                return Err(format!(
+                    "{} can't be crafted on {}",
+                    mod_key, query.item_base
+                ))
+            }
+        }
+    }
+
+    let full_pool = build_pool(repo, query);
+    let locked = match locked_side.as_deref() {
+        Some("prefix") => full_pool.prefixes.clone(),
+        Some(_) => full_pool.suffixes.clone(),
+        None => vec![],
+    }
+    .into_iter()
+    .filter(|m| query.selected_mods.iter().any(|s| s.mod_key == m.mod_key))
+    .collect();
+    let blocked_tags: Vec<String> = meta_crafts
+        .iter()
+        .filter_map(|m| m.blocked_tag())
+        .map(|t| t.to_string())
+        .collect();
+    let pool = full_pool.without_tags(&blocked_tags);
+    let meta = MetaRoll {
+        locked,
+        locked_side,
+        crafted_prefixes: crafts
+            .iter()
+            .filter(|c| c.generation_type == "prefix")
+            .count(),
+        crafted_suffixes: crafts
+            .iter()
+            .filter(|c| c.generation_type == "suffix")
+            .count(),
+    };
+    let matcher = ModMatcher::new(target.clone(), &query.item_base, repo)?;
+    let probability =
+        simulate_meta_rolls(&pool, &matcher, &meta, DEFAULT_SIMULATION_TRIES, is_stale)?
+            .probability;
+    let crafts_cost = crafts.iter().map(|c| cost_in_chaos(&c.cost)).sum::<f64>();
+    let cost_per_try = currency_price(CHAOS) + crafts_cost;
+    let scour_hits_target = match meta.locked_side.as_deref() {
+        Some(side) => Some(scour_hits_target(
+            meta.locked.iter().map(|m| m.mod_key.clone()).collect(),
+            side,
+            crafts.iter().filter(|c| c.generation_type == side).count(),
+            rare_affix_limits(repo.get_item_category(&query.item_base))?,
+            &matcher,
+        )),
+        None => None,
+    };
+    Ok(MetaCraftEstimation {
+        probability,
+        cost_per_try,
+        expected_cost: (probability > 0.0).then(|| cost_per_try / probability),
+        scour_hits_target,
+        scour_cost: meta
+            .locked_side
+            .as_ref()
+            .map(|_| currency_price(SCOUR) + crafts_cost),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::HashMap;
+
+    fn matcher(target: TargetExpr) -> ModMatcher {
+        ModMatcher {
+            target,
+            accepted_modset_by_mod_id: HashMap::from([(
+                "IncreasedLife4".to_string(),
+                HashSet::from(["IncreasedLife4".to_string(), "IncreasedLife5".to_string()]),
+            )]),
+            generation_type_by_mod_id: HashMap::from([
+                ("IncreasedLife4".to_string(), "prefix".to_string()),
+                ("IncreasedLife5".to_string(), "prefix".to_string()),
+            ]),
+            max_prefix_count: 3,
+            max_suffix_count: 3,
+        }
+    }
+
+    #[rstest]
+    #[case(0, 2, true)]
+    #[case(1, 2, false)]
+    #[case(0, 3, false)]
+    fn test_scour_hits_target(
+        #[case] locked_side_crafts: usize,
+        #[case] open_prefixes: usize,
+        #[case] expected: bool,
+    ) {
+        let limits = AffixLimits {
+            prefixes: 3,
+            suffixes: 3,
+        };
+        let target = TargetExpr::And {
+            exprs: vec![
+                TargetExpr::Mod {
+                    mod_id: "IncreasedLife4".to_string(),
+                },
+                TargetExpr::OpenSuffixes { count: 3 },
+                TargetExpr::OpenPrefixes {
+                    count: open_prefixes,
+                },
+            ],
+        };
+        let locked = HashSet::from(["IncreasedLife5".to_string()]);
+        assert_eq!(
+            scour_hits_target(
+                locked,
+                "prefix",
+                locked_side_crafts,
+                limits,
+                &matcher(target)
+            ),
+            expected
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::entities::craft_repo::{ModFamily, ModItem, ModTier};
+use itertools::Itertools;
+
+/// Group mods by family and generation type, tiers are ordered from the best one.
+/// Tier numbers are taken from the base's whole pool, so low item level doesn't shift them.
+/// Families are ordered by prefix/suffix and family name
+pub fn group_by_family(mods: &[ModItem]) -> Vec<ModFamily> {
+    mods.iter()
+        .into_group_map_by(|m| (m.generation_type.clone(), m.family.clone()))
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .map(|((generation_type, family), items)| {
+            let total_weight: u32 = items.iter().map(|m| m.weight).sum();
+            let tiers = items
+                .into_iter()
+                .sorted_by(|a, b| {
+                    b.required_level
+                        .cmp(&a.required_level)
+                        .then_with(|| a.mod_key.cmp(&b.mod_key))
+                })
+                .map(|m| ModTier {
+                    tier: m.tier,
+                    share: match total_weight {
+                        0 => 0.0,
+                        _ => m.weight as f64 / total_weight as f64,
+                    },
+                    mod_item: m.clone(),
+                })
+                .collect();
+            ModFamily {
+                family,
+                generation_type,
+                total_weight,
+                tiers,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn mod_item(
+        mod_key: &str,
+        family: &str,
+        required_level: u64,
+        weight: u32,
+        tier: usize,
+    ) -> ModItem {
+        ModItem {
+            required_level,
+            weight,
+            representation: String::new(),
+            family: family.to_string(),
+            groups: vec![family.to_string()],
+            tier,
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    #[rstest]
+    fn test_group_by_family() {
+        let mods = vec![
+            mod_item("IncreasedLife1", "IncreasedLife", 1, 1000, 9),
+            mod_item("IncreasedMana1", "IncreasedMana", 1, 1000, 12),
+            mod_item("IncreasedLife3", "IncreasedLife", 11, 1000, 7),
+            mod_item("IncreasedLife7", "IncreasedLife", 44, 500, 3),
+        ];
+        let families = group_by_family(&mods);
+        assert_eq!(families.len(), 2);
+        let life = &families[0];
+        assert_eq!(life.family, "IncreasedLife");
+        assert_eq!(life.total_weight, 2500);
+        let tiers: Vec<(usize, &str)> = life
+            .tiers
+            .iter()
+            .map(|t| (t.tier, t.mod_item.mod_key.as_str()))
+            .collect();
+        assert_eq!(
+            tiers,
+            vec![
+                (3, "IncreasedLife7"),
+                (7, "IncreasedLife3"),
+                (9, "IncreasedLife1")
+            ]
+        );
+        assert_eq!(life.tiers[0].share, 0.2);
+    }
+}
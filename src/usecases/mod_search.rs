@@ -0,0 +1,224 @@
+use crate::entities::craft_repo::ModItem;
+use regex::{Regex, RegexBuilder};
+
+/// Part of search query, e.g. "tag:life", "id:IncreasedLife", "stat:base_maximum_life", "re:^\+\d"
+#[derive(Debug)]
+enum SearchTerm {
+    Text(String),
+    Tag(String),
+    ModId(String),
+    StatId(String),
+    Regex(Regex),
+}
+
+/// Parsed search query, every term has to match a mod
+#[derive(Debug)]
+pub struct ModSearch {
+    whole_text: String,
+    terms: Vec<SearchTerm>,
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+/// typos allowed for a word of query, short words have to be exact
+fn allowed_typos(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+impl ModSearch {
+    pub fn parse(query: &str) -> Result<ModSearch, String> {
+        let query = query.trim();
+        let mut terms = vec![];
+        let mut text_words = vec![];
+        let mut rest = query;
+        while !rest.is_empty() {
+            // regex takes the rest of query, it may contain spaces
+            if let Some(pattern) = rest.strip_prefix("re:") {
+                let re = RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("Wrong regex: {}", e))?;
+                terms.push(SearchTerm::Regex(re));
+                break;
+            }
+            let (word, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+            rest = tail.trim_start();
+            let word = word.to_lowercase();
+            let term = match word.split_once(':') {
+                Some(("tag", v)) if !v.is_empty() => SearchTerm::Tag(v.to_string()),
+                Some(("id", v)) if !v.is_empty() => SearchTerm::ModId(v.to_string()),
+                Some(("stat", v)) if !v.is_empty() => SearchTerm::StatId(v.to_string()),
+                _ => {
+                    text_words.push(word.clone());
+                    SearchTerm::Text(word)
+                }
+            };
+            terms.push(term);
+        }
+        Ok(ModSearch {
+            whole_text: text_words.join(" "),
+            terms,
+        })
+    }
+
+    fn score_text(word: &str, m: &ModItem, representation: &str) -> Option<u32> {
+        if representation.contains(word) {
+            return Some(10);
+        }
+        if m.mod_key.to_lowercase().contains(word) {
+            return Some(8);
+        }
+        if m.stat_ids.iter().any(|s| s.contains(word)) {
+            return Some(6);
+        }
+        let typos = allowed_typos(word);
+        representation
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| levenshtein(word, w))
+            .filter(|d| *d <= typos)
+            .min()
+            .map(|d| 5 - d as u32)
+    }
+
+    /// Relevance of mod, None when mod doesn't match
+    pub fn score(&self, m: &ModItem) -> Option<u32> {
+        let representation = m.representation.to_lowercase();
+        let mut score = 0;
+        for term in self.terms.iter() {
+            score += match term {
+                SearchTerm::Text(word) => Self::score_text(word, m, &representation)?,
+                SearchTerm::Tag(tag) => match m.tags.contains(tag) {
+                    true => 5,
+                    false => return None,
+                },
+                SearchTerm::ModId(id) => match m.mod_key.to_lowercase().contains(id) {
+                    true => 10,
+                    false => return None,
+                },
+                SearchTerm::StatId(id) => match m.stat_ids.iter().any(|s| s.contains(id)) {
+                    true => 10,
+                    false => return None,
+                },
+                SearchTerm::Regex(re) => match re.is_match(&m.representation) {
+                    true => 10,
+                    false => return None,
+                },
+            };
+        }
+        // whole phrase match goes first
+        if self.whole_text.contains(' ') && representation.contains(&self.whole_text) {
+            score += 20;
+        }
+        Some(score)
+    }
+}
+
+/// Mods matched by query ordered by relevance, equal ones keep their order
+pub fn search_mods(mods: Vec<ModItem>, query: &str) -> Result<Vec<ModItem>, String> {
+    let search = ModSearch::parse(query)?;
+    if search.terms.is_empty() {
+        return Ok(mods);
+    }
+    let mut scored: Vec<(u32, ModItem)> = mods
+        .into_iter()
+        .filter_map(|m| search.score(&m).map(|s| (s, m)))
+        .collect();
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    Ok(scored.into_iter().map(|(_, m)| m).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn mod_item(mod_key: &str, representation: &str, tag: &str, stat_id: &str) -> ModItem {
+        ModItem {
+            representation: representation.to_string(),
+            tags: vec![tag.to_string()],
+            groups: vec![],
+            stat_ids: vec![stat_id.to_string()],
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn mods() -> Vec<ModItem> {
+        vec![
+            mod_item(
+                "IncreasedLife4",
+                "+(40-49) to maximum Life",
+                "life",
+                "base_maximum_life",
+            ),
+            mod_item(
+                "LifeRegeneration3",
+                "Regenerate (3.3-5) Life per second",
+                "life",
+                "life_regeneration_rate_per_minute",
+            ),
+            mod_item(
+                "MovementVelocity3",
+                "20% increased Movement Speed",
+                "speed",
+                "base_movement_velocity_+%",
+            ),
+            mod_item(
+                "ColdResist2",
+                "+(12-17)% to Cold Resistance",
+                "cold",
+                "base_cold_damage_resistance_%",
+            ),
+        ]
+    }
+
+    fn keys(query: &str) -> Vec<String> {
+        search_mods(mods(), query)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.mod_key)
+            .collect()
+    }
+
+    #[rstest]
+    #[case("movment", vec!["MovementVelocity3"])]
+    #[case("tag:life", vec!["IncreasedLife4", "LifeRegeneration3"])]
+    #[case("stat:base_maximum_life", vec!["IncreasedLife4"])]
+    #[case("id:coldresist", vec!["ColdResist2"])]
+    #[case("re:^\\+\\(\\d+-\\d+\\)% to", vec!["ColdResist2"])]
+    #[case("maximum life", vec!["IncreasedLife4"])]
+    #[case("life", vec!["IncreasedLife4", "LifeRegeneration3"])]
+    #[case("", vec!["IncreasedLife4", "LifeRegeneration3", "MovementVelocity3", "ColdResist2"])]
+    fn test_search_mods(#[case] query: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(keys(query), expected);
+    }
+
+    #[rstest]
+    fn test_wrong_regex() {
+        assert!(search_mods(mods(), "re:(").is_err());
+    }
+
+    #[rstest]
+    #[case("movment", "movement", 1)]
+    #[case("life", "life", 0)]
+    #[case("resistanse", "resistance", 1)]
+    fn test_levenshtein(#[case] a: &str, #[case] b: &str, #[case] expected: usize) {
+        assert_eq!(levenshtein(a, b), expected);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::entities::craft_repo::{ModItem, ModsTableFilter, ModsTableSort};
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Sum of weights of mods by generation type
+pub fn pool_weight_by_generation_type(mods: &[ModItem]) -> HashMap<String, u32> {
+    let mut res = HashMap::new();
+    for m in mods {
+        *res.entry(m.generation_type.clone()).or_insert(0) += m.weight;
+    }
+    res
+}
+
+/// Share of mod in prefix or suffix pool
+pub fn pool_share(m: &ModItem, pool_weight_by_generation_type: &HashMap<String, u32>) -> f64 {
+    match pool_weight_by_generation_type.get(&m.generation_type) {
+        Some(total) if *total > 0 => m.weight as f64 / *total as f64,
+        _ => 0.0,
+    }
+}
+
+/// Sorted unique tags of mods, used as filter chips
+pub fn collect_tags(mods: &[ModItem]) -> Vec<String> {
+    mods.iter()
+        .flat_map(|m| m.tags.iter().cloned())
+        .unique()
+        .sorted()
+        .collect()
+}
+
+pub fn filter_mods(mods: Vec<ModItem>, filter: &ModsTableFilter) -> Vec<ModItem> {
+    mods.into_iter()
+        .filter(|m| {
+            filter
+                .generation_type
+                .as_ref()
+                .is_none_or(|g| &m.generation_type == g)
+        })
+        .filter(|m| filter.tag.as_ref().is_none_or(|t| m.tags.contains(t)))
+        .filter(|m| m.required_level >= filter.min_level)
+        .collect()
+}
+
+/// Stable sort, so order of text search stays for equal values.
+/// Ascending mod key is the order of repo with the best text matches first, kept as is
+pub fn sort_mods(
+    mods: &mut [ModItem],
+    sort: ModsTableSort,
+    descending: bool,
+    pool_weight_by_generation_type: &HashMap<String, u32>,
+) {
+    if sort == ModsTableSort::ModKey && !descending {
+        return;
+    }
+    let compare = |a: &ModItem, b: &ModItem| -> Ordering {
+        match sort {
+            ModsTableSort::ModKey => a.mod_key.to_lowercase().cmp(&b.mod_key.to_lowercase()),
+            ModsTableSort::Weight => a.weight.cmp(&b.weight),
+            ModsTableSort::Share => pool_share(a, pool_weight_by_generation_type)
+                .total_cmp(&pool_share(b, pool_weight_by_generation_type)),
+            ModsTableSort::RequiredLevel => a.required_level.cmp(&b.required_level),
+            ModsTableSort::GenerationType => a.generation_type.cmp(&b.generation_type),
+            ModsTableSort::Tags => a.tags.join(",").cmp(&b.tags.join(",")),
+        }
+    };
+    match descending {
+        true => mods.sort_by(|a, b| compare(b, a)),
+        false => mods.sort_by(compare),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn mod_item(mod_key: &str, generation_type: &str, weight: u32, tag: &str) -> ModItem {
+        ModItem {
+            required_level: weight as u64 / 100,
+            weight,
+            generation_type: generation_type.to_string(),
+            representation: String::new(),
+            tags: vec![tag.to_string()],
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn mods() -> Vec<ModItem> {
+        vec![
+            mod_item("IncreasedLife1", "prefix", 1000, "life"),
+            mod_item("IncreasedMana1", "prefix", 3000, "mana"),
+            mod_item("FireResist1", "suffix", 500, "fire"),
+            mod_item("LifeRegeneration1", "suffix", 1500, "life"),
+        ]
+    }
+
+    #[rstest]
+    #[case(ModsTableSort::Share, true, vec!["IncreasedMana1", "LifeRegeneration1", "IncreasedLife1", "FireResist1"])]
+    #[case(ModsTableSort::Weight, false, vec!["FireResist1", "IncreasedLife1", "LifeRegeneration1", "IncreasedMana1"])]
+    #[case(ModsTableSort::ModKey, true, vec!["LifeRegeneration1", "IncreasedMana1", "IncreasedLife1", "FireResist1"])]
+    fn test_sort_mods(
+        #[case] sort: ModsTableSort,
+        #[case] descending: bool,
+        #[case] expected: Vec<&str>,
+    ) {
+        let mut mods = mods();
+        let pool = pool_weight_by_generation_type(&mods);
+        sort_mods(&mut mods, sort, descending, &pool);
+        let keys: Vec<&str> = mods.iter().map(|m| m.mod_key.as_str()).collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[rstest]
+    #[case(ModsTableFilter { generation_type: Some("suffix".to_string()), tag: None, min_level: 0 }, vec!["FireResist1", "LifeRegeneration1"])]
+    #[case(ModsTableFilter { generation_type: None, tag: Some("life".to_string()), min_level: 0 }, vec!["IncreasedLife1", "LifeRegeneration1"])]
+    #[case(ModsTableFilter { generation_type: None, tag: Some("life".to_string()), min_level: 12 }, vec!["LifeRegeneration1"])]
+    fn test_filter_mods(#[case] filter: ModsTableFilter, #[case] expected: Vec<&str>) {
+        let filtered = filter_mods(mods(), &filter);
+        let keys: Vec<&str> = filtered.iter().map(|m| m.mod_key.as_str()).collect();
+        assert_eq!(keys, expected);
+    }
+}
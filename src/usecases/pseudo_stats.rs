@@ -0,0 +1,249 @@
+use crate::entities::target::PseudoStat;
+use regex::Regex;
+use std::collections::HashMap;
+
+struct PseudoStatRule {
+    pattern: &'static str,
+    contributions: &'static [(PseudoStat, f64)],
+}
+
+use PseudoStat::*;
+
+// order matters, the first matched rule is applied to a mod line
+const RULES: &[PseudoStatRule] = &[
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to all Elemental Resistances$",
+        contributions: &[
+            (TotalFireResistance, 1.0),
+            (TotalColdResistance, 1.0),
+            (TotalLightningResistance, 1.0),
+            (TotalElementalResistance, 3.0),
+            (TotalResistance, 3.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Fire and Cold Resistances$",
+        contributions: &[
+            (TotalFireResistance, 1.0),
+            (TotalColdResistance, 1.0),
+            (TotalElementalResistance, 2.0),
+            (TotalResistance, 2.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Fire and Lightning Resistances$",
+        contributions: &[
+            (TotalFireResistance, 1.0),
+            (TotalLightningResistance, 1.0),
+            (TotalElementalResistance, 2.0),
+            (TotalResistance, 2.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Cold and Lightning Resistances$",
+        contributions: &[
+            (TotalColdResistance, 1.0),
+            (TotalLightningResistance, 1.0),
+            (TotalElementalResistance, 2.0),
+            (TotalResistance, 2.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Fire Resistance$",
+        contributions: &[
+            (TotalFireResistance, 1.0),
+            (TotalElementalResistance, 1.0),
+            (TotalResistance, 1.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Cold Resistance$",
+        contributions: &[
+            (TotalColdResistance, 1.0),
+            (TotalElementalResistance, 1.0),
+            (TotalResistance, 1.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Lightning Resistance$",
+        contributions: &[
+            (TotalLightningResistance, 1.0),
+            (TotalElementalResistance, 1.0),
+            (TotalResistance, 1.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Fire and Chaos Resistances$",
+        contributions: &[
+            (TotalFireResistance, 1.0),
+            (TotalChaosResistance, 1.0),
+            (TotalElementalResistance, 1.0),
+            (TotalResistance, 2.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Cold and Chaos Resistances$",
+        contributions: &[
+            (TotalColdResistance, 1.0),
+            (TotalChaosResistance, 1.0),
+            (TotalElementalResistance, 1.0),
+            (TotalResistance, 2.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Lightning and Chaos Resistances$",
+        contributions: &[
+            (TotalLightningResistance, 1.0),
+            (TotalChaosResistance, 1.0),
+            (TotalElementalResistance, 1.0),
+            (TotalResistance, 2.0),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?)% to Chaos Resistance$",
+        contributions: &[(TotalChaosResistance, 1.0), (TotalResistance, 1.0)],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to maximum Life$",
+        contributions: &[(TotalLife, 1.0)],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to maximum Mana$",
+        contributions: &[(TotalMana, 1.0)],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to maximum Energy Shield$",
+        contributions: &[(TotalEnergyShield, 1.0)],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to all Attributes$",
+        contributions: &[
+            (TotalStrength, 1.0),
+            (TotalDexterity, 1.0),
+            (TotalIntelligence, 1.0),
+            (TotalAttributes, 3.0),
+            (TotalLife, 0.5),
+            (TotalMana, 0.5),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to Strength and Dexterity$",
+        contributions: &[
+            (TotalStrength, 1.0),
+            (TotalDexterity, 1.0),
+            (TotalAttributes, 2.0),
+            (TotalLife, 0.5),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to Strength and Intelligence$",
+        contributions: &[
+            (TotalStrength, 1.0),
+            (TotalIntelligence, 1.0),
+            (TotalAttributes, 2.0),
+            (TotalLife, 0.5),
+            (TotalMana, 0.5),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to Dexterity and Intelligence$",
+        contributions: &[
+            (TotalDexterity, 1.0),
+            (TotalIntelligence, 1.0),
+            (TotalAttributes, 2.0),
+            (TotalMana, 0.5),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to Strength$",
+        contributions: &[
+            (TotalStrength, 1.0),
+            (TotalAttributes, 1.0),
+            (TotalLife, 0.5),
+        ],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to Dexterity$",
+        contributions: &[(TotalDexterity, 1.0), (TotalAttributes, 1.0)],
+    },
+    PseudoStatRule {
+        pattern: r"^\+?(\d+(?:\.\d+)?) to Intelligence$",
+        contributions: &[
+            (TotalIntelligence, 1.0),
+            (TotalAttributes, 1.0),
+            (TotalMana, 0.5),
+        ],
+    },
+];
+
+/// Compiled pseudo stat rules, create it once and reuse for every item
+pub struct PseudoStatsCalculator {
+    rules: Vec<(Regex, &'static [(PseudoStat, f64)])>,
+    roll_range_re: Regex,
+}
+
+impl Default for PseudoStatsCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PseudoStatsCalculator {
+    pub fn new() -> PseudoStatsCalculator {
+        PseudoStatsCalculator {
+            rules: RULES
+                .iter()
+                .map(|r| {
+                    (
+                        Regex::new(r.pattern).expect("regexp error in pseudo stat rule"),
+                        r.contributions,
+                    )
+                })
+                .collect(),
+            roll_range_re: Regex::new(r"(\d)\([\d.\-]+\)")
+                .expect("regexp error during roll range cleaning"),
+        }
+    }
+
+    /// Sum pseudo stats of mod texts.
+    /// Texts may contain roll ranges from advanced item description, "+12(12-17)% to Cold Resistance"
+    pub fn calculate(&self, mod_texts: &[String]) -> HashMap<PseudoStat, f64> {
+        let mut res = HashMap::new();
+        for line in mod_texts.iter().flat_map(|t| t.split('\n')) {
+            let line = self.roll_range_re.replace_all(line.trim(), "$1");
+            for (re, contributions) in self.rules.iter() {
+                let value = match re.captures(&line).and_then(|c| c[1].parse::<f64>().ok()) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                for (stat, multiplier) in contributions.iter() {
+                    *res.entry(*stat).or_insert(0.0) += value * multiplier;
+                }
+                break;
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec!["+12(12-17)% to Cold Resistance", "+21% to Fire Resistance"], PseudoStat::TotalElementalResistance, 33.0)]
+    #[case(vec!["+10% to all Elemental Resistances", "+16(16-20)% to Chaos Resistance"], PseudoStat::TotalResistance, 46.0)]
+    #[case(vec!["+28(20-29) to maximum Life", "+20 to Strength"], PseudoStat::TotalLife, 38.0)]
+    #[case(vec!["+3(3-9) to maximum Life\n+13 to Strength and Intelligence"], PseudoStat::TotalAttributes, 26.0)]
+    fn test_pseudo_stats(
+        #[case] mod_texts: Vec<&str>,
+        #[case] stat: PseudoStat,
+        #[case] expected: f64,
+    ) {
+        let calculator = PseudoStatsCalculator::new();
+        let texts: Vec<String> = mod_texts.into_iter().map(String::from).collect();
+        let stats = calculator.calculate(&texts);
+        assert_eq!(stats.get(&stat), Some(&expected));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::entities::craft_repo::{AffixLimits, ItemCategory};
+
+/// Rarity of crafted item, normal items have no affixes to estimate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rarity {
+    Magic,
+    Rare,
+}
+
+/// (prefix count, suffix count, ratio) of rolled affixes
+pub type AffixCountVariant = (usize, usize, f64);
+
+// chaos orb on equipment, ratios are measured
+const RARE_EQUIPMENT_VARIANTS: &[AffixCountVariant] = &[
+    (1, 3, 0.2814),
+    (2, 2, 0.2836),
+    (2, 3, 0.1725),
+    (3, 1, 0.1016),
+    (3, 2, 0.0775),
+    (3, 3, 0.0833),
+];
+
+/// Magic items of every category have one prefix and one suffix at most
+pub const MAGIC_LIMITS: AffixLimits = AffixLimits {
+    prefixes: 1,
+    suffixes: 1,
+};
+
+const MAGIC_VARIANTS: &[AffixCountVariant] = &[(1, 0, 0.25), (0, 1, 0.25), (1, 1, 0.5)];
+
+// rare jewels have 3 or 4 mods, ratios are approximate
+const RARE_JEWEL_VARIANTS: &[AffixCountVariant] = &[(1, 2, 0.325), (2, 1, 0.325), (2, 2, 0.35)];
+
+/// Affix caps of rare item, None for items which can't be rare
+pub fn rare_limits(category: ItemCategory) -> Option<AffixLimits> {
+    let (prefixes, suffixes) = match category {
+        ItemCategory::Flask => return None,
+        ItemCategory::Equipment => (3, 3),
+        ItemCategory::Jewel | ItemCategory::AbyssJewel | ItemCategory::ClusterJewel => (2, 2),
+    };
+    Some(AffixLimits { prefixes, suffixes })
+}
+
+/// Caps of rare item rolled by chaos orb, error for items which can't be rare
+pub fn rare_affix_limits(category: ItemCategory) -> Result<AffixLimits, String> {
+    rare_limits(category).ok_or_else(|| format!("{:?} can't be rare", category))
+}
+
+/// Affix caps of item of the rarity, None when item can't have it
+pub fn affix_limits(category: ItemCategory, rarity: Rarity) -> Option<AffixLimits> {
+    match rarity {
+        Rarity::Magic => Some(MAGIC_LIMITS),
+        Rarity::Rare => rare_limits(category),
+    }
+}
+
+/// Caps of the highest rarity item can have, flasks are magic at most
+pub fn max_affix_limits(category: ItemCategory) -> AffixLimits {
+    rare_limits(category).unwrap_or(MAGIC_LIMITS)
+}
+
+/// Affix counts rolled by chaos orb, empty when item can't be rare
+pub fn chaos_variants(category: ItemCategory) -> &'static [AffixCountVariant] {
+    match category {
+        ItemCategory::Flask => &[],
+        ItemCategory::Equipment => RARE_EQUIPMENT_VARIANTS,
+        ItemCategory::Jewel | ItemCategory::AbyssJewel | ItemCategory::ClusterJewel => {
+            RARE_JEWEL_VARIANTS
+        }
+    }
+}
+
+/// Whether chaos orb ratios of category are guessed rather than measured
+pub fn chaos_ratios_are_approximate(category: ItemCategory) -> bool {
+    matches!(
+        category,
+        ItemCategory::Jewel | ItemCategory::AbyssJewel | ItemCategory::ClusterJewel
+    )
+}
+
+/// Affix counts rolled by alteration or chaos orb, empty when item can't have the rarity
+pub fn affix_count_variants(
+    category: ItemCategory,
+    rarity: Rarity,
+) -> &'static [AffixCountVariant] {
+    match rarity {
+        Rarity::Magic => MAGIC_VARIANTS,
+        Rarity::Rare => chaos_variants(category),
+    }
+}
+
+/// Ratio of one affix count variant, 0 for impossible ones
+pub fn affix_count_ratio(
+    category: ItemCategory,
+    rarity: Rarity,
+    prefix_count: usize,
+    suffix_count: usize,
+) -> f64 {
+    affix_count_variants(category, rarity)
+        .iter()
+        .find(|(pc, sc, _)| *pc == prefix_count && *sc == suffix_count)
+        .map_or(0.0, |(_, _, ratio)| *ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(ItemCategory::Equipment, 6)]
+    #[case(ItemCategory::Jewel, 3)]
+    #[case(ItemCategory::AbyssJewel, 3)]
+    #[case(ItemCategory::ClusterJewel, 3)]
+    #[case(ItemCategory::Flask, 0)]
+    fn test_chaos_variants_fit_limits(#[case] category: ItemCategory, #[case] count: usize) {
+        let variants = chaos_variants(category);
+        assert_eq!(variants.len(), count);
+        match rare_limits(category) {
+            Some(limits) => {
+                assert!(variants
+                    .iter()
+                    .all(|(pc, sc, _)| *pc <= limits.prefixes && *sc <= limits.suffixes));
+                let total: f64 = variants.iter().map(|(_, _, r)| r).sum();
+                assert!((total - 1.0).abs() < 0.01);
+            }
+            None => assert!(rare_affix_limits(category).is_err()),
+        }
+    }
+
+    #[rstest]
+    #[case(ItemCategory::Flask)]
+    #[case(ItemCategory::Jewel)]
+    #[case(ItemCategory::Equipment)]
+    fn test_magic_rules(#[case] category: ItemCategory) {
+        assert_eq!(affix_limits(category, Rarity::Magic), Some(MAGIC_LIMITS));
+        assert_eq!(affix_count_ratio(category, Rarity::Magic, 1, 1), 0.5);
+        assert_eq!(affix_count_ratio(category, Rarity::Magic, 2, 0), 0.0);
+    }
+
+    #[rstest]
+    #[case(ItemCategory::Equipment, false)]
+    #[case(ItemCategory::Flask, false)]
+    #[case(ItemCategory::Jewel, true)]
+    #[case(ItemCategory::ClusterJewel, true)]
+    fn test_chaos_ratios_are_approximate(#[case] category: ItemCategory, #[case] expected: bool) {
+        assert_eq!(chaos_ratios_are_approximate(category), expected);
+    }
+
+    #[rstest]
+    fn test_max_affix_limits() {
+        assert_eq!(max_affix_limits(ItemCategory::Flask), MAGIC_LIMITS);
+        assert_eq!(
+            max_affix_limits(ItemCategory::ClusterJewel),
+            AffixLimits {
+                prefixes: 2,
+                suffixes: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,529 @@
+use crate::entities::craft_repo::{
+    CraftRepo, ItemCategory, ModItem, ModsQuery, Reforge, ReforgeKind,
+};
+use crate::usecases::matcher::{check_matching, CraftedItem, ModMatcher};
+use crate::usecases::pseudo_stats::PseudoStatsCalculator;
+use crate::usecases::rules::{chaos_variants, rare_limits};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+
+const SIMULATION_SEED: u64 = 42;
+pub const DEFAULT_SIMULATION_TRIES: u32 = 20_000;
+// how often simulation checks that its result is still needed
+const STALE_CHECK_TRIES: u32 = 1_000;
+// weight multiplier of "more likely" reforge, "less likely" divides by it
+const REFORGE_LIKELIHOOD_MULTIPLIER: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct PoolMod {
+    pub mod_key: String,
+    pub generation_type: String,
+    pub weight: u32,
+    pub groups: Vec<String>,
+    pub tags: Vec<String>,
+    pub representation: String,
+}
+
+/// Mods which can be rolled on item, with groups required for exclusion
+#[derive(Debug, Clone)]
+pub struct RollPool {
+    pub prefixes: Vec<PoolMod>,
+    pub suffixes: Vec<PoolMod>,
+    pub category: ItemCategory,
+}
+
+impl RollPool {
+    pub fn new(available_mods: &[ModItem]) -> RollPool {
+        let mut prefixes = vec![];
+        let mut suffixes = vec![];
+        for m in available_mods {
+            let pool_mod = PoolMod {
+                mod_key: m.mod_key.clone(),
+                generation_type: m.generation_type.clone(),
+                weight: m.weight,
+                groups: m.groups.clone(),
+                tags: m.tags.clone(),
+                representation: m.representation.clone(),
+            };
+            match m.generation_type.as_str() {
+                "prefix" => prefixes.push(pool_mod),
+                "suffix" => suffixes.push(pool_mod),
+                _ => (),
+            }
+        }
+        RollPool {
+            prefixes,
+            suffixes,
+            category: ItemCategory::default(),
+        }
+    }
+
+    /// Pool with weights of tagged mods changed by "more/less likely" reforge
+    pub fn with_reforge(&self, reforge: &Reforge) -> RollPool {
+        let reweight = |mods: &[PoolMod]| -> Vec<PoolMod> {
+            mods.iter()
+                .map(|m| {
+                    let mut m = m.clone();
+                    if m.tags.contains(&reforge.tag) {
+                        m.weight = match reforge.kind {
+                            ReforgeKind::Guaranteed => m.weight,
+                            ReforgeKind::MoreLikely => m.weight * REFORGE_LIKELIHOOD_MULTIPLIER,
+                            ReforgeKind::LessLikely => m.weight / REFORGE_LIKELIHOOD_MULTIPLIER,
+                        };
+                    }
+                    m
+                })
+                .collect()
+        };
+        RollPool {
+            prefixes: reweight(&self.prefixes),
+            suffixes: reweight(&self.suffixes),
+            category: self.category,
+        }
+    }
+
+    /// Pool without mods of tags, e.g. "attack" for "Cannot roll Attack Modifiers"
+    pub fn without_tags(&self, tags: &[String]) -> RollPool {
+        let keep = |mods: &[PoolMod]| -> Vec<PoolMod> {
+            mods.iter()
+                .filter(|m| !m.tags.iter().any(|t| tags.contains(t)))
+                .cloned()
+                .collect()
+        };
+        RollPool {
+            prefixes: keep(&self.prefixes),
+            suffixes: keep(&self.suffixes),
+            category: self.category,
+        }
+    }
+}
+
+/// Affix counts of chaos roll, items which can't be rare get no affixes
+fn pick_variant(category: ItemCategory, rng: &mut StdRng) -> (usize, usize) {
+    let variants = chaos_variants(category);
+    let total: f64 = variants.iter().map(|(_, _, r)| r).sum();
+    if total <= 0.0 {
+        return (0, 0);
+    }
+    let mut point = rng.gen_range(0.0..total);
+    for (pc, sc, ratio) in variants.iter() {
+        if point < *ratio {
+            return (*pc, *sc);
+        }
+        point -= ratio;
+    }
+    let (pc, sc, _) = variants.last().unwrap();
+    (*pc, *sc)
+}
+
+/// Roll `count` mods from pool without repeating mod groups
+pub fn roll_affixes(
+    pool: &[PoolMod],
+    count: usize,
+    used_groups: &mut HashSet<String>,
+    rng: &mut impl Rng,
+    rolled: &mut HashSet<String>,
+) {
+    for _ in 0..count {
+        let available = pool
+            .iter()
+            .filter(|m| !m.groups.iter().any(|g| used_groups.contains(g)))
+            .collect::<Vec<&PoolMod>>();
+        let total: u32 = available.iter().map(|m| m.weight).sum();
+        if total == 0 {
+            return;
+        }
+        let mut point = rng.gen_range(0..total);
+        for m in available {
+            if point < m.weight {
+                used_groups.extend(m.groups.iter().cloned());
+                rolled.insert(m.mod_key.clone());
+                break;
+            }
+            point -= m.weight;
+        }
+    }
+}
+
+/// Replace every "(min-max)" range of representation with a random value from it
+fn roll_values(representation: &str, range_re: &Regex, rng: &mut impl Rng) -> String {
+    range_re
+        .replace_all(representation, |c: &Captures| {
+            let min = c[1].parse::<f64>().unwrap_or(0.0);
+            let max = c[2].parse::<f64>().unwrap_or(min);
+            if min >= max {
+                return c[1].to_string();
+            }
+            match c[1].contains('.') || c[2].contains('.') {
+                true => format!("{:.1}", rng.gen_range(min..=max)),
+                false => rng.gen_range(min as i64..=max as i64).to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Builds crafted items from rolled mods, values are rolled only when target needs them
+pub struct RolledItemBuilder {
+    with_values: bool,
+    range_re: Regex,
+    calculator: PseudoStatsCalculator,
+}
+
+impl RolledItemBuilder {
+    pub fn new(matcher: &ModMatcher) -> RolledItemBuilder {
+        RolledItemBuilder {
+            with_values: matcher.target.uses_pseudo_stats(),
+            range_re: Regex::new(r"\((\d+(?:\.\d+)?)-(\d+(?:\.\d+)?)\)")
+                .expect("regexp error during value rolling"),
+            calculator: PseudoStatsCalculator::new(),
+        }
+    }
+
+    pub fn build(
+        &self,
+        pool: &RollPool,
+        rolled: HashSet<String>,
+        rng: &mut impl Rng,
+    ) -> CraftedItem {
+        if !self.with_values {
+            return CraftedItem::from_mod_ids(rolled);
+        }
+        let texts: Vec<String> = pool
+            .prefixes
+            .iter()
+            .chain(pool.suffixes.iter())
+            .filter(|m| rolled.contains(&m.mod_key))
+            .map(|m| roll_values(&m.representation, &self.range_re, rng))
+            .collect();
+        CraftedItem {
+            mod_ids: rolled,
+            pseudo_stats: self.calculator.calculate(&texts),
+            ..CraftedItem::default()
+        }
+    }
+}
+
+/// Share of rolls which satisfy matcher's target and share of rolls with every target mod
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationResult {
+    pub probability: f64,
+    pub probability_by_mod_id: HashMap<String, f64>,
+}
+
+/// Roll affixes of rare item, guaranteed reforge rolls a mod with its tag first
+fn roll_rare_item(pool: &RollPool, reforge: Option<&Reforge>, rng: &mut StdRng) -> HashSet<String> {
+    let (mut prefix_count, mut suffix_count) = pick_variant(pool.category, rng);
+    let mut used_groups = HashSet::new();
+    let mut rolled = HashSet::new();
+    if let Some(Reforge {
+        tag,
+        kind: ReforgeKind::Guaranteed,
+    }) = reforge
+    {
+        let tagged = pool
+            .prefixes
+            .iter()
+            .chain(pool.suffixes.iter())
+            .filter(|m| m.tags.contains(tag))
+            .cloned()
+            .collect::<Vec<PoolMod>>();
+        roll_affixes(&tagged, 1, &mut used_groups, rng, &mut rolled);
+        match tagged.iter().find(|m| rolled.contains(&m.mod_key)) {
+            Some(m) if m.generation_type == "prefix" => {
+                prefix_count = prefix_count.saturating_sub(1)
+            }
+            Some(_) => suffix_count = suffix_count.saturating_sub(1),
+            None => (),
+        }
+    }
+    roll_affixes(
+        &pool.prefixes,
+        prefix_count,
+        &mut used_groups,
+        rng,
+        &mut rolled,
+    );
+    roll_affixes(
+        &pool.suffixes,
+        suffix_count,
+        &mut used_groups,
+        rng,
+        &mut rolled,
+    );
+    rolled
+}
+
+/// Rare item rerolled by chaos orb with meta-crafts of bench
+#[derive(Debug, Clone, Default)]
+pub struct MetaRoll {
+    /// mods of locked side, they stay on item
+    pub locked: Vec<PoolMod>,
+    pub locked_side: Option<String>,
+    /// slots taken by crafted meta mods
+    pub crafted_prefixes: usize,
+    pub crafted_suffixes: usize,
+}
+
+/// Reroll unlocked affixes, total mod count follows chaos variants
+fn roll_meta_item(pool: &RollPool, meta: &MetaRoll, rng: &mut StdRng) -> HashSet<String> {
+    let mut rolled: HashSet<String> = meta.locked.iter().map(|m| m.mod_key.clone()).collect();
+    let limits = match rare_limits(pool.category) {
+        Some(limits) => limits,
+        None => return rolled,
+    };
+    let (prefix_count, suffix_count) = pick_variant(pool.category, rng);
+    let mut used_groups: HashSet<String> = meta
+        .locked
+        .iter()
+        .flat_map(|m| m.groups.iter().cloned())
+        .collect();
+    let rest = (prefix_count + suffix_count).saturating_sub(meta.locked.len());
+    let (prefix_count, suffix_count) = match meta.locked_side.as_deref() {
+        Some("prefix") => (0, rest.max(1)),
+        Some(_) => (rest.max(1), 0),
+        None => (prefix_count, suffix_count),
+    };
+    roll_affixes(
+        &pool.prefixes,
+        prefix_count.min(limits.prefixes.saturating_sub(meta.crafted_prefixes)),
+        &mut used_groups,
+        rng,
+        &mut rolled,
+    );
+    roll_affixes(
+        &pool.suffixes,
+        suffix_count.min(limits.suffixes.saturating_sub(meta.crafted_suffixes)),
+        &mut used_groups,
+        rng,
+        &mut rolled,
+    );
+    rolled
+}
+
+/// Share of chaos rolls of item with meta-crafts which satisfy matcher's target
+pub fn simulate_meta_rolls(
+    pool: &RollPool,
+    matcher: &ModMatcher,
+    meta: &MetaRoll,
+    tries: u32,
+    is_stale: &dyn Fn() -> bool,
+) -> Result<SimulationResult, String> {
+    simulate_with(pool, matcher, tries, is_stale, |rng| {
+        roll_meta_item(pool, meta, rng)
+    })
+}
+
+pub fn simulate_rolls(
+    pool: &RollPool,
+    matcher: &ModMatcher,
+    reforge: Option<&Reforge>,
+    tries: u32,
+    is_stale: &dyn Fn() -> bool,
+) -> Result<SimulationResult, String> {
+    let pool = match reforge {
+        Some(reforge) => pool.with_reforge(reforge),
+        None => pool.clone(),
+    };
+    simulate_with(&pool, matcher, tries, is_stale, |rng| {
+        roll_rare_item(&pool, reforge, rng)
+    })
+}
+
+/// Rolls are stopped when result is not needed anymore, e.g. ui sent a newer request
+fn simulate_with(
+    pool: &RollPool,
+    matcher: &ModMatcher,
+    tries: u32,
+    is_stale: &dyn Fn() -> bool,
+    mut roll: impl FnMut(&mut StdRng) -> HashSet<String>,
+) -> Result<SimulationResult, String> {
+    let mut rng = StdRng::seed_from_u64(SIMULATION_SEED);
+    let item_builder = RolledItemBuilder::new(matcher);
+    let target_mod_ids = matcher.target.mod_ids();
+    let mut hits = 0;
+    let mut hits_by_mod_id: HashMap<String, u32> = HashMap::new();
+    for i in 0..tries {
+        if i % STALE_CHECK_TRIES == 0 && is_stale() {
+            return Err("simulation is stopped".to_string());
+        }
+        let rolled = roll(&mut rng);
+        let crafted = item_builder.build(pool, rolled, &mut rng);
+        if check_matching(matcher, &crafted) {
+            hits += 1;
+        }
+        for mod_id in target_mod_ids.iter() {
+            if matcher.is_mod_matched(mod_id, &crafted) {
+                *hits_by_mod_id.entry(mod_id.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+    debug!("simulation: {} hits of {} tries", hits, tries);
+    Ok(SimulationResult {
+        probability: hits as f64 / tries as f64,
+        probability_by_mod_id: target_mod_ids
+            .into_iter()
+            .map(|m| {
+                let hits = hits_by_mod_id.get(&m).copied().unwrap_or(0);
+                (m, hits as f64 / tries as f64)
+            })
+            .collect(),
+    })
+}
+
+/// Share of chaos rolls which satisfy matcher's target
+pub fn simulate_chaos_rolls(
+    pool: &RollPool,
+    matcher: &ModMatcher,
+    tries: u32,
+    is_stale: &dyn Fn() -> bool,
+) -> Result<f64, String> {
+    Ok(simulate_rolls(pool, matcher, None, tries, is_stale)?.probability)
+}
+
+/// Pool of all mods available for item base and level of query
+pub fn build_pool(repo: &impl CraftRepo, query: &ModsQuery) -> RollPool {
+    let available_mods_query = ModsQuery {
+        string_query: "".to_string(),
+        item_base: query.item_base.clone(),
+        item_level: query.item_level,
+        selected_mods: vec![],
+        influences: query.influences.clone(),
+        cluster_enchant: query.cluster_enchant.clone(),
+    };
+    RollPool {
+        category: repo.get_item_category(&query.item_base),
+        ..RollPool::new(&repo.find_mods(&available_mods_query))
+    }
+}
+
+pub fn simulate_chaos_for_matcher(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    matcher: &ModMatcher,
+    is_stale: &dyn Fn() -> bool,
+) -> Result<f64, String> {
+    let pool = build_pool(repo, query);
+    simulate_chaos_rolls(&pool, matcher, DEFAULT_SIMULATION_TRIES, is_stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::target::TargetExpr;
+    use rstest::rstest;
+
+    fn pool_mod(mod_key: &str, generation_type: &str, weight: u32, tag: &str) -> PoolMod {
+        PoolMod {
+            mod_key: mod_key.to_string(),
+            generation_type: generation_type.to_string(),
+            weight,
+            groups: vec![mod_key.to_string()],
+            tags: vec![tag.to_string()],
+            representation: String::new(),
+        }
+    }
+
+    fn pool() -> RollPool {
+        RollPool {
+            prefixes: vec![
+                pool_mod("IncreasedLife1", "prefix", 100, "life"),
+                pool_mod("IncreasedMana1", "prefix", 1000, "mana"),
+                pool_mod("IncreasedMana2", "prefix", 1000, "mana"),
+                pool_mod("IncreasedMana3", "prefix", 1000, "mana"),
+            ],
+            suffixes: vec![
+                pool_mod("FireResist1", "suffix", 1000, "fire"),
+                pool_mod("ColdResist1", "suffix", 1000, "cold"),
+                pool_mod("LightningResist1", "suffix", 1000, "lightning"),
+            ],
+            category: ItemCategory::Equipment,
+        }
+    }
+
+    fn matcher() -> ModMatcher {
+        ModMatcher {
+            target: TargetExpr::Mod {
+                mod_id: "IncreasedLife1".to_string(),
+            },
+            accepted_modset_by_mod_id: HashMap::from([(
+                "IncreasedLife1".to_string(),
+                HashSet::from(["IncreasedLife1".to_string()]),
+            )]),
+            generation_type_by_mod_id: HashMap::new(),
+            max_prefix_count: 3,
+            max_suffix_count: 3,
+        }
+    }
+
+    #[rstest]
+    #[case(ReforgeKind::Guaranteed, 1.0, 1.0)]
+    #[case(ReforgeKind::MoreLikely, 0.3, 1.0)]
+    fn test_reforge_raises_target_probability(
+        #[case] kind: ReforgeKind,
+        #[case] min: f64,
+        #[case] max: f64,
+    ) {
+        let reforge = Reforge {
+            tag: "life".to_string(),
+            kind,
+        };
+        let chaos = simulate_rolls(&pool(), &matcher(), None, 2_000, &|| false).unwrap();
+        let reforged =
+            simulate_rolls(&pool(), &matcher(), Some(&reforge), 2_000, &|| false).unwrap();
+        assert!(reforged.probability > chaos.probability);
+        assert!(reforged.probability >= min && reforged.probability <= max);
+        assert_eq!(
+            reforged.probability_by_mod_id.get("IncreasedLife1"),
+            Some(&reforged.probability)
+        );
+    }
+
+    #[rstest]
+    fn test_stale_simulation_is_stopped() {
+        assert!(simulate_rolls(&pool(), &matcher(), None, 2_000, &|| true).is_err());
+    }
+
+    #[rstest]
+    fn test_less_likely_reforge() {
+        let reforge = Reforge {
+            tag: "life".to_string(),
+            kind: ReforgeKind::LessLikely,
+        };
+        let chaos = simulate_rolls(&pool(), &matcher(), None, 2_000, &|| false).unwrap();
+        let reforged =
+            simulate_rolls(&pool(), &matcher(), Some(&reforge), 2_000, &|| false).unwrap();
+        assert!(reforged.probability < chaos.probability);
+    }
+
+    #[rstest]
+    fn test_locked_prefixes_are_kept() {
+        let pool = pool();
+        let meta = MetaRoll {
+            locked: vec![pool.prefixes[0].clone()],
+            locked_side: Some("prefix".to_string()),
+            crafted_prefixes: 0,
+            crafted_suffixes: 1,
+        };
+        let chaos = simulate_rolls(&pool, &matcher(), None, 2_000, &|| false).unwrap();
+        let locked = simulate_meta_rolls(&pool, &matcher(), &meta, 2_000, &|| false).unwrap();
+        assert!(chaos.probability < 1.0);
+        assert_eq!(locked.probability, 1.0);
+    }
+
+    #[rstest]
+    fn test_pool_without_tags() {
+        let pool = pool().without_tags(&["life".to_string(), "fire".to_string()]);
+        assert_eq!(pool.prefixes.len(), 3);
+        assert_eq!(pool.suffixes.len(), 2);
+        let meta = MetaRoll::default();
+        assert_eq!(
+            simulate_meta_rolls(&pool, &matcher(), &meta, 500, &|| false)
+                .unwrap()
+                .probability,
+            0.0
+        );
+    }
+}
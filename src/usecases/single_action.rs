@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use crate::entities::craft_repo::{
+    AffixLimits, CraftRepo, ModItem, ModsQuery, SingleActionEstimation,
+};
+use crate::entities::target::TargetExpr;
+use crate::usecases::matcher::{check_matching, check_rollable_target, CraftedItem, ModMatcher};
+use crate::usecases::rules::rare_affix_limits;
+use crate::usecases::simulation::{
+    build_pool, simulate_meta_rolls, MetaRoll, DEFAULT_SIMULATION_TRIES,
+};
+
+fn crafted(mod_ids: &HashSet<String>) -> CraftedItem {
+    CraftedItem::from_mod_ids(mod_ids.clone())
+}
+
+fn matched_target_mods(matcher: &ModMatcher, item: &CraftedItem) -> usize {
+    matcher
+        .target
+        .mod_ids()
+        .iter()
+        .filter(|t| matcher.is_mod_matched(t, item))
+        .count()
+}
+
+/// Exalted orb outcomes by weight of candidates: (adds one of target mods, item hits target)
+pub fn exalt_outcomes(
+    existing: &HashSet<String>,
+    candidates: &[ModItem],
+    matcher: &ModMatcher,
+) -> (f64, f64) {
+    let total: u32 = candidates.iter().map(|m| m.weight).sum();
+    if total == 0 {
+        return (0.0, 0.0);
+    }
+    let matched_before = matched_target_mods(matcher, &crafted(existing));
+    let mut adds_target = 0;
+    let mut hits_target = 0;
+    for m in candidates.iter() {
+        let mut mod_ids = existing.clone();
+        mod_ids.insert(m.mod_key.clone());
+        let item = crafted(&mod_ids);
+        if matched_target_mods(matcher, &item) > matched_before {
+            adds_target += m.weight;
+        }
+        if check_matching(matcher, &item) {
+            hits_target += m.weight;
+        }
+    }
+    (
+        adds_target as f64 / total as f64,
+        hits_target as f64 / total as f64,
+    )
+}
+
+/// Annulment removes one mod at random: (removes mod not needed by target, item still hits target)
+pub fn annul_outcomes(existing: &HashSet<String>, matcher: &ModMatcher) -> (f64, f64) {
+    if existing.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mut unwanted = 0;
+    let mut keeps_target = 0;
+    for removed in existing.iter() {
+        if !matcher
+            .accepted_modset_by_mod_id
+            .values()
+            .any(|accepted| accepted.contains(removed))
+        {
+            unwanted += 1;
+        }
+        let mut mod_ids = existing.clone();
+        mod_ids.remove(removed);
+        if check_matching(matcher, &crafted(&mod_ids)) {
+            keeps_target += 1;
+        }
+    }
+    (
+        unwanted as f64 / existing.len() as f64,
+        keeps_target as f64 / existing.len() as f64,
+    )
+}
+
+/// Aisling annuls one mod at random and adds veiled mod to a side with open slot,
+/// the veiled mod is never a target: chance of item to hit target after it
+pub fn aisling_outcome(
+    affixes: &[(String, String)],
+    limits: AffixLimits,
+    matcher: &ModMatcher,
+) -> f64 {
+    if affixes.is_empty() {
+        return 0.0;
+    }
+    let veiled_prefix = matcher.clone().with_affix_limits(AffixLimits {
+        prefixes: limits.prefixes.saturating_sub(1),
+        ..limits
+    });
+    let veiled_suffix = matcher.clone().with_affix_limits(AffixLimits {
+        suffixes: limits.suffixes.saturating_sub(1),
+        ..limits
+    });
+    let mut hits = 0.0;
+    for removed in 0..affixes.len() {
+        let rest: Vec<&(String, String)> = affixes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != removed)
+            .map(|(_, a)| a)
+            .collect();
+        let count_of = |gen_type: &str| rest.iter().filter(|(_, g)| g == gen_type).count();
+        let sides: Vec<&ModMatcher> = [
+            (count_of("prefix") < limits.prefixes, &veiled_prefix),
+            (count_of("suffix") < limits.suffixes, &veiled_suffix),
+        ]
+        .into_iter()
+        .filter_map(|(is_open, m)| is_open.then_some(m))
+        .collect();
+        if sides.is_empty() {
+            continue;
+        }
+        let item = crafted(&rest.iter().map(|(m, _)| m.clone()).collect());
+        let hit_sides = sides.iter().filter(|m| check_matching(m, &item)).count();
+        hits += hit_sides as f64 / sides.len() as f64;
+    }
+    hits / affixes.len() as f64
+}
+
+/// One currency used on current item, affixes are (mod id, generation type) of parsed item.
+/// Groups of existing mods are excluded from exalted orb pool
+pub fn calculate_single_actions(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    target: &TargetExpr,
+    affixes: &[(String, String)],
+    is_stale: &dyn Fn() -> bool,
+) -> Result<SingleActionEstimation, String> {
+    if affixes.is_empty() {
+        return Err("no explicit mods on item".to_string());
+    }
+    check_rollable_target(target)?;
+    let count_of = |gen_type: &str| affixes.iter().filter(|(_, g)| g == gen_type).count();
+    let limits = rare_affix_limits(repo.get_item_category(&query.item_base))?;
+    let open_prefixes = limits.prefixes.saturating_sub(count_of("prefix"));
+    let open_suffixes = limits.suffixes.saturating_sub(count_of("suffix"));
+    let existing: HashSet<String> = affixes.iter().map(|(m, _)| m.clone()).collect();
+
+    let pool_query = ModsQuery {
+        string_query: "".to_string(),
+        selected_mods: vec![],
+        ..query.clone()
+    };
+    let existing_mods: Vec<ModItem> = repo
+        .find_mods(&pool_query)
+        .into_iter()
+        .filter(|m| existing.contains(&m.mod_key))
+        .collect();
+    let candidates: Vec<ModItem> = repo
+        .find_mods(&ModsQuery {
+            selected_mods: existing_mods,
+            ..pool_query.clone()
+        })
+        .into_iter()
+        .filter(|m| match m.generation_type.as_str() {
+            "prefix" => open_prefixes > 0,
+            "suffix" => open_suffixes > 0,
+            _ => false,
+        })
+        .collect();
+
+    let matcher = ModMatcher::new(target.clone(), &query.item_base, repo)?;
+    let (exalt_adds_target, exalt_hits_target) = exalt_outcomes(&existing, &candidates, &matcher);
+    let (annul_removes_unwanted, annul_keeps_target) = annul_outcomes(&existing, &matcher);
+    let aisling_hits_target = aisling_outcome(affixes, limits, &matcher);
+
+    // veiled mod takes a prefix or a suffix slot with the same chance
+    let pool = build_pool(repo, query);
+    let veiled = |crafted_prefixes: usize, crafted_suffixes: usize| {
+        let meta = MetaRoll {
+            crafted_prefixes,
+            crafted_suffixes,
+            ..MetaRoll::default()
+        };
+        simulate_meta_rolls(&pool, &matcher, &meta, DEFAULT_SIMULATION_TRIES, is_stale)
+            .map(|r| r.probability)
+    };
+    let veiled_chaos_hits_target = (veiled(1, 0)? + veiled(0, 1)?) / 2.0;
+
+    Ok(SingleActionEstimation {
+        open_prefixes,
+        open_suffixes,
+        exalt_adds_target,
+        exalt_hits_target,
+        annul_removes_unwanted,
+        annul_keeps_target,
+        aisling_hits_target,
+        veiled_chaos_hits_target,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::HashMap;
+
+    fn mod_item(mod_key: &str, generation_type: &str, weight: u32) -> ModItem {
+        ModItem {
+            weight,
+            generation_type: generation_type.to_string(),
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn matcher() -> ModMatcher {
+        let target = TargetExpr::And {
+            exprs: vec![
+                TargetExpr::Mod {
+                    mod_id: "IncreasedLife4".to_string(),
+                },
+                TargetExpr::Mod {
+                    mod_id: "FireResist3".to_string(),
+                },
+            ],
+        };
+        ModMatcher {
+            target,
+            accepted_modset_by_mod_id: HashMap::from([
+                (
+                    "IncreasedLife4".to_string(),
+                    HashSet::from(["IncreasedLife4".to_string(), "IncreasedLife5".to_string()]),
+                ),
+                (
+                    "FireResist3".to_string(),
+                    HashSet::from(["FireResist3".to_string()]),
+                ),
+            ]),
+            generation_type_by_mod_id: HashMap::from([
+                ("IncreasedLife4".to_string(), "prefix".to_string()),
+                ("IncreasedLife5".to_string(), "prefix".to_string()),
+                ("FireResist3".to_string(), "suffix".to_string()),
+                ("ColdResist3".to_string(), "suffix".to_string()),
+            ]),
+            max_prefix_count: 3,
+            max_suffix_count: 3,
+        }
+    }
+
+    #[rstest]
+    fn test_exalt_outcomes() {
+        let existing = HashSet::from(["IncreasedLife4".to_string()]);
+        let candidates = vec![
+            mod_item("FireResist3", "suffix", 1000),
+            mod_item("ColdResist3", "suffix", 3000),
+        ];
+        let (adds_target, hits_target) = exalt_outcomes(&existing, &candidates, &matcher());
+        assert_eq!(adds_target, 0.25);
+        assert_eq!(hits_target, 0.25);
+        assert_eq!(exalt_outcomes(&existing, &[], &matcher()), (0.0, 0.0));
+    }
+
+    #[rstest]
+    fn test_annul_outcomes() {
+        let existing = HashSet::from([
+            "IncreasedLife5".to_string(),
+            "FireResist3".to_string(),
+            "ColdResist3".to_string(),
+            "IncreasedMana1".to_string(),
+        ]);
+        let (removes_unwanted, keeps_target) = annul_outcomes(&existing, &matcher());
+        assert_eq!(removes_unwanted, 0.5);
+        assert_eq!(keeps_target, 0.5);
+    }
+
+    #[rstest]
+    fn test_aisling_outcome() {
+        let affix = |mod_key: &str, generation_type: &str| {
+            (mod_key.to_string(), generation_type.to_string())
+        };
+        let affixes = vec![
+            affix("IncreasedLife5", "prefix"),
+            affix("FireResist3", "suffix"),
+            affix("ColdResist3", "suffix"),
+        ];
+        let limits = AffixLimits {
+            prefixes: 2,
+            suffixes: 2,
+        };
+        // veiled mod is harmless when target doesn't need open slots
+        let life_only = ModMatcher {
+            target: TargetExpr::Mod {
+                mod_id: "IncreasedLife4".to_string(),
+            },
+            ..matcher()
+        };
+        assert!((aisling_outcome(&affixes, limits, &life_only) - 2.0 / 3.0).abs() < 1e-9);
+        // resist removed: veiled prefix keeps suffix open, veiled suffix takes it
+        let open_suffix = ModMatcher {
+            target: TargetExpr::And {
+                exprs: vec![
+                    TargetExpr::Mod {
+                        mod_id: "IncreasedLife4".to_string(),
+                    },
+                    TargetExpr::OpenSuffixes { count: 1 },
+                ],
+            },
+            ..matcher()
+        };
+        assert!((aisling_outcome(&affixes, limits, &open_suffix) - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(aisling_outcome(&[], limits, &open_suffix), 0.0);
+    }
+}
@@ -0,0 +1,389 @@
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+
+use crate::entities::craft_repo::{
+    AffixLimits, BenchCraft, CraftRepo, MagicEstimation, MetaCraft, ModItem, ModsQuery, Recipe,
+    RecipeStep, StepFailure,
+};
+use crate::entities::target::TargetExpr;
+use crate::usecases::craft_searcher::{get_bench_crafts, get_set_of_better_mods};
+use crate::usecases::currency::{
+    cost_in_chaos, currency_price, ALTERATION, AUGMENTATION, CHAOS, REGAL,
+};
+use crate::usecases::estimation::{calculate_estimation_for_target, calculate_magic_estimation};
+use crate::usecases::meta_crafting::calculate_meta_craft_estimation;
+use crate::usecases::rules::MAGIC_LIMITS;
+
+/// Chaos orbs expected to pass all steps, None when some step can't succeed
+pub fn expected_cost(steps: &[RecipeStep]) -> Option<f64> {
+    steps.iter().try_fold(0.0, |spent, step| {
+        if step.probability <= 0.0 {
+            return None;
+        }
+        Some(match step.on_fail {
+            StepFailure::Retry => spent + step.cost / step.probability,
+            // everything spent before is lost on every failure
+            StepFailure::Restart => (spent + step.cost) / step.probability,
+        })
+    })
+}
+
+pub fn recipe(name: &str, steps: Vec<RecipeStep>) -> Recipe {
+    Recipe {
+        name: name.to_string(),
+        expected_cost: expected_cost(&steps),
+        steps,
+    }
+}
+
+/// Cheaper recipes go first, unfinishable ones are the last
+pub fn sort_recipes(recipes: &mut [Recipe]) {
+    recipes.sort_by(|a, b| match (a.expected_cost, b.expected_cost) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+}
+
+fn bench_step(craft: &BenchCraft) -> RecipeStep {
+    RecipeStep {
+        name: format!("bench craft {}", craft.representation.replace('\n', ", ")),
+        probability: 1.0,
+        cost: cost_in_chaos(&craft.cost),
+        on_fail: StepFailure::Retry,
+    }
+}
+
+/// Selected mod which can be crafted on bench with its cheapest craft.
+/// Only crafts in the set of target and better mods of selected mod fit
+fn find_bench_craft<'a>(
+    selected: &'a [ModItem],
+    crafts: &'a [BenchCraft],
+    better_mods_by_mod_id: &HashMap<String, HashSet<String>>,
+) -> Option<(&'a ModItem, &'a BenchCraft)> {
+    selected.iter().find_map(|m| {
+        let better_mods = better_mods_by_mod_id.get(&m.mod_key)?;
+        crafts
+            .iter()
+            .filter(|c| c.generation_type == m.generation_type && better_mods.contains(&c.mod_key))
+            .min_by(|a, b| cost_in_chaos(&a.cost).total_cmp(&cost_in_chaos(&b.cost)))
+            .map(|c| (m, c))
+    })
+}
+
+/// Selected mods which target requires all together, None for targets
+/// with other expressions which recipes of single mods can't aim at
+fn plain_target_mods(selected: &[ModItem], target: &TargetExpr) -> Option<Vec<ModItem>> {
+    target
+        .as_plain_mods()?
+        .iter()
+        .map(|mod_id| selected.iter().find(|m| &m.mod_key == mod_id).cloned())
+        .collect()
+}
+
+fn fits_limits(mods: &[ModItem], limits: AffixLimits) -> bool {
+    let count = |gen_type: &str| {
+        mods.iter()
+            .filter(|m| m.generation_type == gen_type)
+            .count()
+    };
+    count("prefix") <= limits.prefixes && count("suffix") <= limits.suffixes
+}
+
+/// Alteration spam with augmentation, regal adds the third mod.
+/// Regal is skipped when magic item already is the target,
+/// bench craft keeps it as after regal every affix side has an open slot
+fn magic_steps(
+    estimation: &MagicEstimation,
+    needs_regal: bool,
+    bench: Option<&BenchCraft>,
+) -> Vec<RecipeStep> {
+    let mut steps = vec![RecipeStep {
+        name: "alteration spam, augmentation when worth it".to_string(),
+        probability: estimation.augmentation_probability,
+        cost: currency_price(ALTERATION)
+            + estimation.augmentation_usage * currency_price(AUGMENTATION),
+        on_fail: StepFailure::Retry,
+    }];
+    if needs_regal || bench.is_some() {
+        steps.push(RecipeStep {
+            name: "regal orb".to_string(),
+            probability: estimation.regal_probability.unwrap_or(1.0),
+            cost: currency_price(REGAL),
+            on_fail: StepFailure::Restart,
+        });
+    }
+    steps.extend(bench.map(bench_step));
+    steps
+}
+
+fn magic_recipe(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    bench: Option<&BenchCraft>,
+) -> Option<Recipe> {
+    let estimation = calculate_magic_estimation(repo, query).ok()?;
+    let needs_regal = !fits_limits(&query.selected_mods, MAGIC_LIMITS);
+    let name = match (bench, needs_regal) {
+        (Some(_), _) => "Alteration + regal + bench craft",
+        (None, true) => "Alteration + regal",
+        (None, false) => "Alteration",
+    };
+    Some(recipe(name, magic_steps(&estimation, needs_regal, bench)))
+}
+
+fn chaos_step(probability: f64) -> RecipeStep {
+    RecipeStep {
+        name: "chaos orb spam".to_string(),
+        probability,
+        cost: currency_price(CHAOS),
+        on_fail: StepFailure::Retry,
+    }
+}
+
+/// Known recipes for target on item of query, cheapest first.
+/// Recipes which can't reach the target on this item are skipped,
+/// alteration and bench recipes need target of plain selected mods
+pub fn plan_recipes(
+    repo: &impl CraftRepo,
+    query: &ModsQuery,
+    target: &TargetExpr,
+    meta_crafts: &[MetaCraft],
+    is_stale: &dyn Fn() -> bool,
+) -> Result<Vec<Recipe>, String> {
+    if query.selected_mods.is_empty() {
+        return Err("no mods selected".to_string());
+    }
+    let mut recipes = vec![];
+    let chaos = calculate_estimation_for_target(repo, query, target, is_stale)?;
+    recipes.push(recipe("Chaos spam", vec![chaos_step(chaos.probability)]));
+    let plain_mods = plain_target_mods(&query.selected_mods, target);
+    if let Some(mods) = &plain_mods {
+        let plain_query = ModsQuery {
+            selected_mods: mods.clone(),
+            ..query.clone()
+        };
+        recipes.extend(magic_recipe(repo, &plain_query, None));
+    }
+    if let Ok(estimation) =
+        calculate_meta_craft_estimation(repo, query, target, meta_crafts, is_stale)
+    {
+        let labels = meta_crafts.iter().map(|m| m.label()).join(", ");
+        recipes.push(recipe(
+            "Chaos with meta-crafts",
+            vec![RecipeStep {
+                name: format!("craft {} and chaos orb", labels),
+                probability: estimation.probability,
+                cost: estimation.cost_per_try,
+                on_fail: StepFailure::Retry,
+            }],
+        ));
+    }
+
+    let Some(plain_mods) = plain_mods else {
+        sort_recipes(&mut recipes);
+        return Ok(recipes);
+    };
+    let crafts = get_bench_crafts(repo, &query.item_base);
+    let better_mods_by_mod_id = plain_mods
+        .iter()
+        .filter_map(|m| {
+            let better_mods = get_set_of_better_mods(repo, &m.mod_key, &query.item_base).ok()?;
+            Some((m.mod_key.clone(), better_mods))
+        })
+        .collect();
+    if let Some((bench_mod, craft)) = find_bench_craft(&plain_mods, &crafts, &better_mods_by_mod_id)
+    {
+        let rest: Vec<ModItem> = plain_mods
+            .iter()
+            .filter(|m| m.mod_key != bench_mod.mod_key)
+            .cloned()
+            .collect();
+        let rest_query = ModsQuery {
+            selected_mods: rest.clone(),
+            ..query.clone()
+        };
+        // chaos has to leave a slot for bench craft
+        let open_slot = match bench_mod.generation_type.as_str() {
+            "prefix" => TargetExpr::OpenPrefixes { count: 1 },
+            _ => TargetExpr::OpenSuffixes { count: 1 },
+        };
+        let mut exprs = match TargetExpr::all_of(&rest) {
+            TargetExpr::And { exprs } => exprs,
+            expr => vec![expr],
+        };
+        exprs.push(open_slot);
+        let rest_target = TargetExpr::And { exprs };
+        if let Ok(estimation) =
+            calculate_estimation_for_target(repo, &rest_query, &rest_target, is_stale)
+        {
+            recipes.push(recipe(
+                "Chaos + bench craft",
+                vec![chaos_step(estimation.probability), bench_step(craft)],
+            ));
+        }
+        if !rest.is_empty() {
+            recipes.extend(magic_recipe(repo, &rest_query, Some(craft)));
+        }
+    }
+    sort_recipes(&mut recipes);
+    Ok(recipes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::craft_repo::CurrencyCost;
+    use rstest::rstest;
+
+    fn step(probability: f64, cost: f64, on_fail: StepFailure) -> RecipeStep {
+        RecipeStep {
+            name: "step".to_string(),
+            probability,
+            cost,
+            on_fail,
+        }
+    }
+
+    #[rstest]
+    #[case(vec![step(0.1, 1.0, StepFailure::Retry)], Some(10.0))]
+    #[case(vec![step(0.1, 1.0, StepFailure::Retry), step(1.0, 4.0, StepFailure::Retry)], Some(14.0))]
+    #[case(vec![step(0.1, 1.0, StepFailure::Retry), step(0.5, 2.0, StepFailure::Restart)], Some(24.0))]
+    #[case(vec![step(0.1, 1.0, StepFailure::Retry), step(0.0, 2.0, StepFailure::Restart)], None)]
+    fn test_expected_cost(#[case] steps: Vec<RecipeStep>, #[case] expected: Option<f64>) {
+        let cost = expected_cost(&steps);
+        match (cost, expected) {
+            (Some(c), Some(e)) => assert!((c - e).abs() < 1e-9),
+            _ => assert_eq!(cost, expected),
+        }
+    }
+
+    #[rstest]
+    fn test_sort_recipes() {
+        let mut recipes = vec![
+            recipe("never", vec![step(0.0, 1.0, StepFailure::Retry)]),
+            recipe("expensive", vec![step(0.01, 1.0, StepFailure::Retry)]),
+            recipe("cheap", vec![step(0.5, 1.0, StepFailure::Retry)]),
+        ];
+        sort_recipes(&mut recipes);
+        let names: Vec<&str> = recipes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["cheap", "expensive", "never"]);
+    }
+
+    fn bench_craft(mod_key: &str, chaos: u32) -> BenchCraft {
+        BenchCraft {
+            mod_key: mod_key.to_string(),
+            family: "IncreasedLife".to_string(),
+            generation_type: "prefix".to_string(),
+            representation: mod_key.to_string(),
+            cost: vec![CurrencyCost {
+                currency: CHAOS.to_string(),
+                count: chaos,
+            }],
+        }
+    }
+
+    fn prefix(mod_key: &str) -> ModItem {
+        ModItem {
+            generation_type: "prefix".to_string(),
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn suffix(mod_key: &str) -> ModItem {
+        ModItem {
+            generation_type: "suffix".to_string(),
+            ..ModItem::for_test(mod_key)
+        }
+    }
+
+    fn mod_expr(mod_id: &str) -> TargetExpr {
+        TargetExpr::Mod {
+            mod_id: mod_id.to_string(),
+        }
+    }
+
+    #[rstest]
+    fn test_plain_target_mods() {
+        let selected = vec![prefix("IncreasedLife4"), suffix("FireResist3")];
+        let and = TargetExpr::all_of(&selected);
+        let mods = plain_target_mods(&selected, &and).unwrap();
+        assert_eq!(mods.len(), 2);
+        // alteration recipe would aim at both mods, OR needs one of them
+        let or = TargetExpr::Or {
+            exprs: vec![mod_expr("IncreasedLife4"), mod_expr("FireResist3")],
+        };
+        assert!(plain_target_mods(&selected, &or).is_none());
+        let single = mod_expr("FireResist3");
+        let mods = plain_target_mods(&selected, &single).unwrap();
+        assert_eq!(mods[0].mod_key, "FireResist3");
+        // target mod which isn't selected can't be looked up
+        assert!(plain_target_mods(&selected, &mod_expr("ColdResist3")).is_none());
+    }
+
+    #[rstest]
+    #[case(vec![prefix("IncreasedLife4")], true)]
+    #[case(vec![prefix("IncreasedLife4"), suffix("FireResist3")], true)]
+    #[case(vec![prefix("IncreasedLife4"), prefix("LocalPhysical5")], false)]
+    fn test_fits_magic_limits(#[case] mods: Vec<ModItem>, #[case] expected: bool) {
+        assert_eq!(fits_limits(&mods, MAGIC_LIMITS), expected);
+    }
+
+    #[rstest]
+    fn test_magic_steps() {
+        let estimation = MagicEstimation {
+            alteration_probability: 0.1,
+            augmentation_probability: 0.2,
+            augmentation_usage: 0.5,
+            expected_alterations: 5.0,
+            expected_augmentations: 2.5,
+            regal_probability: None,
+        };
+        assert_eq!(magic_steps(&estimation, false, None).len(), 1);
+        assert_eq!(magic_steps(&estimation, true, None).len(), 2);
+        let craft = bench_craft("EinharMasterIncreasedLife3", 4);
+        let names: Vec<String> = magic_steps(&estimation, false, Some(&craft))
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names[1], "regal orb");
+        assert_eq!(names.len(), 3);
+    }
+
+    #[rstest]
+    fn test_find_bench_craft() {
+        let selected = vec![ModItem {
+            family: "IncreasedLife".to_string(),
+            ..ModItem::for_test("IncreasedLife4")
+        }];
+        let crafts = vec![
+            bench_craft("EinharMasterIncreasedLife1", 1),
+            bench_craft("EinharMasterIncreasedLife3", 4),
+            bench_craft("EinharMasterIncreasedLife4", 8),
+        ];
+        let better_mods = |mod_ids: &[&str]| {
+            HashMap::from([(
+                "IncreasedLife4".to_string(),
+                mod_ids.iter().map(|m| m.to_string()).collect(),
+            )])
+        };
+        // lower tier craft of the same family doesn't satisfy target
+        let found = find_bench_craft(
+            &selected,
+            &crafts,
+            &better_mods(&["IncreasedLife4", "IncreasedLife5"]),
+        );
+        assert!(found.is_none());
+        let found = find_bench_craft(
+            &selected,
+            &crafts,
+            &better_mods(&[
+                "IncreasedLife4",
+                "EinharMasterIncreasedLife3",
+                "EinharMasterIncreasedLife4",
+            ]),
+        );
+        assert_eq!(found.unwrap().1.mod_key, "EinharMasterIncreasedLife3");
+    }
+}